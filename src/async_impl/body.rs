@@ -2,11 +2,12 @@ use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use bytes::Bytes;
 use futures_core::Stream;
 use http_body::Body as HttpBody;
-use tokio::time::Delay;
+use tokio::time::{Delay, Instant};
 
 /// An asynchronous request body.
 pub struct Body {
@@ -27,9 +28,16 @@ enum Inner {
             >,
         >,
         timeout: Option<Delay>,
+        read_timeout: Option<ReadTimeout>,
     },
 }
 
+/// Fails a response body when no data arrives for `dur`.
+struct ReadTimeout {
+    dur: Duration,
+    delay: Delay,
+}
+
 struct WrapStream<S>(S);
 
 struct WrapHyper(hyper::Body);
@@ -93,15 +101,21 @@ impl Body {
             inner: Inner::Streaming {
                 body,
                 timeout: None,
+                read_timeout: None,
             },
         }
     }
 
-    pub(crate) fn response(body: hyper::Body, timeout: Option<Delay>) -> Body {
+    pub(crate) fn response(
+        body: hyper::Body,
+        timeout: Option<Delay>,
+        read_timeout: Option<Duration>,
+    ) -> Body {
         Body {
             inner: Inner::Streaming {
                 body: Box::pin(WrapHyper(body)),
                 timeout,
+                read_timeout: read_timeout.map(ReadTimeout::new),
             },
         }
     }
@@ -112,6 +126,7 @@ impl Body {
             inner: Inner::Streaming {
                 body: Box::pin(WrapHyper(body)),
                 timeout: None,
+                read_timeout: None,
             },
         }
     }
@@ -195,6 +210,28 @@ impl fmt::Debug for Body {
     }
 }
 
+// ===== impl ReadTimeout =====
+
+impl ReadTimeout {
+    fn new(dur: Duration) -> ReadTimeout {
+        ReadTimeout {
+            dur,
+            delay: tokio::time::delay_for(dur),
+        }
+    }
+
+    /// Restarts the timer when data is ready, and otherwise tells whether
+    /// it elapsed while waiting.
+    fn elapsed(&mut self, pending: bool, cx: &mut Context) -> bool {
+        if pending {
+            Pin::new(&mut self.delay).poll(cx).is_ready()
+        } else {
+            self.delay.reset(Instant::now() + self.dur);
+            false
+        }
+    }
+}
+
 // ===== impl ImplStream =====
 
 impl HttpBody for ImplStream {
@@ -209,13 +246,20 @@ impl HttpBody for ImplStream {
             Inner::Streaming {
                 ref mut body,
                 ref mut timeout,
+                ref mut read_timeout,
             } => {
                 if let Some(ref mut timeout) = timeout {
                     if let Poll::Ready(()) = Pin::new(timeout).poll(cx) {
                         return Poll::Ready(Some(Err(crate::error::body(crate::error::TimedOut))));
                     }
                 }
-                futures_core::ready!(Pin::new(body).poll_data(cx))
+                let data = Pin::new(body).poll_data(cx);
+                if let Some(ref mut read_timeout) = read_timeout {
+                    if read_timeout.elapsed(data.is_pending(), cx) {
+                        return Poll::Ready(Some(Err(crate::error::body(crate::error::TimedOut))));
+                    }
+                }
+                futures_core::ready!(data)
                     .map(|opt_chunk| opt_chunk.map(Into::into).map_err(crate::error::body))
             }
             Inner::Reusable(ref mut bytes) => {
@@ -264,13 +308,20 @@ impl Stream for ImplStream {
             Inner::Streaming {
                 ref mut body,
                 ref mut timeout,
+                ref mut read_timeout,
             } => {
                 if let Some(ref mut timeout) = timeout {
                     if let Poll::Ready(()) = Pin::new(timeout).poll(cx) {
                         return Poll::Ready(Some(Err(crate::error::body(crate::error::TimedOut))));
                     }
                 }
-                futures_core::ready!(Pin::new(body).poll_data(cx))
+                let data = Pin::new(body).poll_data(cx);
+                if let Some(ref mut read_timeout) = read_timeout {
+                    if read_timeout.elapsed(data.is_pending(), cx) {
+                        return Poll::Ready(Some(Err(crate::error::body(crate::error::TimedOut))));
+                    }
+                }
+                futures_core::ready!(data)
                     .map(|opt_chunk| opt_chunk.map(Into::into).map_err(crate::error::body))
             }
             Inner::Reusable(ref mut bytes) => {
@@ -1,9 +1,15 @@
+#[cfg(feature = "__tls")]
+use std::any::Any;
+use std::collections::HashMap;
 use std::convert::TryInto;
-use std::net::IpAddr;
-use std::sync::Arc;
-#[cfg(feature = "cookies")]
-use std::sync::RwLock;
-use std::time::Duration;
+use std::error::Error as StdError;
+#[cfg(target_os = "linux")]
+use std::ffi::CString;
+use std::net::{IpAddr, SocketAddr};
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use std::{fmt, str};
 
 use bytes::Bytes;
@@ -14,27 +20,42 @@ use http::header::{
 use http::Uri;
 use http::uri::Scheme;
 use hyper::client::ResponseFuture;
+use hyper::service::Service;
 #[cfg(feature = "native-tls-crate")]
 use native_tls_crate::TlsConnector;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::time::Delay;
 
 use log::debug;
 
+use super::decoder::Accepts;
 use super::request::{Request, RequestBuilder};
 use super::response::Response;
 use super::Body;
-use crate::connect::Connector;
+use crate::connect::{Connector, CustomConnector, TcpOptions};
+#[cfg(unix)]
+use crate::connect::UnixConnector;
 #[cfg(feature = "cookies")]
 use crate::cookie;
+#[cfg(not(feature = "trust-dns"))]
+use crate::dns::GaiResolver;
+#[cfg(feature = "trust-dns")]
+use crate::dns::TrustDnsResolver;
+use crate::dns::{AddressFamily, DnsResolverWithOverrides, DynResolver, Resolve};
 use crate::into_url::{expect_uri, try_uri};
+use crate::middleware::{self, Middleware};
+use crate::proxy_auth::ProxyChallenge;
 use crate::redirect::{self, remove_sensitive_headers};
+use crate::retry;
+use crate::stats::{ConnInfo, Counters, InFlight, Stats};
+use crate::timings::Timings;
 #[cfg(feature = "__tls")]
-use crate::tls::TlsBackend;
+use crate::tls::{CertificatePins, TlsBackend};
 #[cfg(feature = "__tls")]
-use crate::{Certificate, Identity};
+use crate::{Certificate, Identity, TlsVersion};
 use crate::{IntoUrl, Method, Proxy, StatusCode, Url};
 
 /// An asynchronous `Client` to make Requests with.
@@ -45,6 +66,9 @@ use crate::{IntoUrl, Method, Proxy, StatusCode, Url};
 ///
 /// The `Client` holds a connection pool internally, so it is advised that
 /// you create one and **reuse** it.
+///
+/// The `Client` is also a `tower::Service<Request>`, so it can be wrapped
+/// by tower layers such as timeouts or concurrency limits.
 #[derive(Clone)]
 pub struct Client {
     inner: Arc<ClientRef>,
@@ -57,33 +81,52 @@ pub struct ClientBuilder {
 
 struct Config {
     // NOTE: When adding a new field, update `fmt::Debug for ClientBuilder`
-    gzip: bool,
+    accepts: Accepts,
     headers: HeaderMap,
     #[cfg(feature = "native-tls")]
     hostname_verification: bool,
     #[cfg(feature = "__tls")]
     certs_verification: bool,
     connect_timeout: Option<Duration>,
-    max_idle_per_host: usize,
+    read_timeout: Option<Duration>,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: usize,
     #[cfg(feature = "__tls")]
     identity: Option<Identity>,
     proxies: Vec<Proxy>,
     auto_sys_proxy: bool,
     redirect_policy: redirect::Policy,
     referer: bool,
+    retry_policy: retry::Policy,
+    middleware: Vec<Arc<dyn Middleware>>,
     timeout: Option<Duration>,
     #[cfg(feature = "__tls")]
     root_certs: Vec<Certificate>,
     #[cfg(feature = "__tls")]
+    tls_built_in_root_certs: bool,
+    #[cfg(feature = "__tls")]
+    certificate_pins: CertificatePins,
+    #[cfg(feature = "__tls")]
+    min_tls_version: Option<TlsVersion>,
+    #[cfg(feature = "__tls")]
+    max_tls_version: Option<TlsVersion>,
+    #[cfg(feature = "__tls")]
+    tls_cipher_suites: Option<Vec<String>>,
+    #[cfg(feature = "__tls")]
     tls: TlsBackend,
     http2_only: bool,
     http1_title_case_headers: bool,
     http2_initial_stream_window_size: Option<u32>,
     http2_initial_connection_window_size: Option<u32>,
-    local_address: Option<IpAddr>,
+    tcp: TcpOptions,
     nodelay: bool,
+    address_family: AddressFamily,
+    happy_eyeballs_timeout: Option<Duration>,
+    connector: Option<CustomConnector>,
     #[cfg(feature = "cookies")]
     cookie_store: Option<cookie::CookieStore>,
+    dns_resolver: Option<Arc<dyn Resolve>>,
+    dns_overrides: HashMap<String, Vec<SocketAddr>>,
     error: Option<crate::Error>,
 }
 
@@ -104,22 +147,36 @@ impl ClientBuilder {
         ClientBuilder {
             config: Config {
                 error: None,
-                gzip: cfg!(feature = "gzip"),
+                accepts: Accepts::default(),
                 headers,
                 #[cfg(feature = "native-tls")]
                 hostname_verification: true,
                 #[cfg(feature = "__tls")]
                 certs_verification: true,
                 connect_timeout: None,
-                max_idle_per_host: std::usize::MAX,
+                read_timeout: None,
+                pool_idle_timeout: Some(Duration::from_secs(90)),
+                pool_max_idle_per_host: std::usize::MAX,
                 proxies: Vec::new(),
                 auto_sys_proxy: true,
                 redirect_policy: redirect::Policy::default(),
+                retry_policy: retry::Policy::default(),
+                middleware: Vec::new(),
                 referer: true,
                 timeout: None,
                 #[cfg(feature = "__tls")]
                 root_certs: Vec::new(),
                 #[cfg(feature = "__tls")]
+                tls_built_in_root_certs: true,
+                #[cfg(feature = "__tls")]
+                certificate_pins: CertificatePins::default(),
+                #[cfg(feature = "__tls")]
+                min_tls_version: None,
+                #[cfg(feature = "__tls")]
+                max_tls_version: None,
+                #[cfg(feature = "__tls")]
+                tls_cipher_suites: None,
+                #[cfg(feature = "__tls")]
                 identity: None,
                 #[cfg(feature = "__tls")]
                 tls: TlsBackend::default(),
@@ -127,10 +184,15 @@ impl ClientBuilder {
                 http1_title_case_headers: false,
                 http2_initial_stream_window_size: None,
                 http2_initial_connection_window_size: None,
-                local_address: None,
+                tcp: TcpOptions::default(),
                 nodelay: false,
+                address_family: AddressFamily::Any,
+                happy_eyeballs_timeout: Some(Duration::from_millis(300)),
+                connector: None,
                 #[cfg(feature = "cookies")]
                 cookie_store: None,
+                dns_resolver: None,
+                dns_overrides: HashMap::new(),
             },
         }
     }
@@ -154,12 +216,44 @@ impl ClientBuilder {
         }
         let proxies = Arc::new(proxies);
 
+        let mut resolver: Arc<dyn Resolve> = match config.dns_resolver {
+            Some(resolver) => resolver,
+            None => default_resolver()?,
+        };
+        if !config.dns_overrides.is_empty() {
+            resolver = Arc::new(DnsResolverWithOverrides::new(
+                resolver,
+                config.dns_overrides,
+            ));
+        }
+        let resolver = DynResolver::new(resolver, config.address_family);
+
         let mut connector = {
             #[cfg(feature = "__tls")]
             fn user_agent(headers: &HeaderMap) -> Option<HeaderValue> {
                 headers.get(USER_AGENT).cloned()
             }
 
+            #[cfg(feature = "__rustls")]
+            fn set_alpn(tls: &mut rustls::ClientConfig, http2_only: bool) {
+                if http2_only {
+                    tls.set_protocols(&["h2".into()]);
+                } else {
+                    tls.set_protocols(&["h2".into(), "http/1.1".into()]);
+                }
+            }
+
+            #[cfg(feature = "__tls")]
+            {
+                if let (Some(min), Some(max)) = (config.min_tls_version, config.max_tls_version) {
+                    if min > max {
+                        return Err(crate::error::builder(
+                            "minimum TLS version is greater than the maximum",
+                        ));
+                    }
+                }
+            }
+
             #[cfg(feature = "__tls")]
             match config.tls {
                 #[cfg(feature = "default-tls")]
@@ -173,6 +267,28 @@ impl ClientBuilder {
 
                     tls.danger_accept_invalid_certs(!config.certs_verification);
 
+                    tls.disable_built_in_roots(!config.tls_built_in_root_certs);
+
+                    // native-tls has no TLS 1.3 protocol, but allows it when
+                    // there is no maximum.
+                    if config.min_tls_version == Some(TlsVersion::TLS_1_3) {
+                        return Err(crate::error::builder(
+                            "native-tls doesn't support a minimum of TLS 1.3",
+                        ));
+                    }
+                    tls.min_protocol_version(
+                        config.min_tls_version.and_then(TlsVersion::to_native_tls),
+                    );
+                    tls.max_protocol_version(
+                        config.max_tls_version.and_then(TlsVersion::to_native_tls),
+                    );
+
+                    if config.tls_cipher_suites.is_some() {
+                        return Err(crate::error::builder(
+                            "native-tls doesn't support selecting cipher suites",
+                        ));
+                    }
+
                     for cert in config.root_certs {
                         cert.add_to_native_tls(&mut tls);
                     }
@@ -186,25 +302,69 @@ impl ClientBuilder {
                     }
 
                     Connector::new_default_tls(
-                        tls,
+                        resolver,
+                        tls.build().map_err(crate::error::builder)?,
                         proxies.clone(),
                         user_agent(&config.headers),
-                        config.local_address,
+                        config.tcp,
                         config.nodelay,
                     )?
                 }
-                #[cfg(feature = "rustls-tls")]
+                #[cfg(feature = "default-tls")]
+                TlsBackend::BuiltNativeTls(tls) => Connector::new_default_tls(
+                    resolver,
+                    tls,
+                    proxies.clone(),
+                    user_agent(&config.headers),
+                    config.tcp,
+                    config.nodelay,
+                )?,
+                #[cfg(feature = "__rustls")]
                 TlsBackend::Rustls => {
                     use crate::tls::NoVerifier;
 
                     let mut tls = rustls::ClientConfig::new();
-                    if config.http2_only {
-                        tls.set_protocols(&["h2".into()]);
-                    } else {
-                        tls.set_protocols(&["h2".into(), "http/1.1".into()]);
+                    set_alpn(&mut tls, config.http2_only);
+                    if config.tls_built_in_root_certs {
+                        #[cfg(feature = "rustls-tls-webpki-roots")]
+                        tls.root_store
+                            .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+
+                        #[cfg(feature = "rustls-tls-native-roots")]
+                        {
+                            let native = rustls_native_certs::load_native_certs()
+                                .map_err(crate::error::builder)?;
+                            tls.root_store.roots.extend(native.roots);
+                        }
+                    }
+
+                    let min = config.min_tls_version.unwrap_or(TlsVersion::TLS_1_0);
+                    let max = config.max_tls_version.unwrap_or(TlsVersion::TLS_1_3);
+                    tls.versions.retain(|&version| match TlsVersion::from_rustls(version) {
+                        Some(version) => min <= version && version <= max,
+                        None => false,
+                    });
+                    if tls.versions.is_empty() {
+                        return Err(crate::error::builder(
+                            "rustls only supports TLS 1.2 and 1.3",
+                        ));
+                    }
+
+                    if let Some(names) = config.tls_cipher_suites {
+                        let mut suites = Vec::with_capacity(names.len());
+                        for name in names {
+                            match crate::tls::rustls_cipher_suite(&name) {
+                                Some(suite) => suites.push(suite),
+                                None => {
+                                    return Err(crate::error::builder(format!(
+                                        "unsupported cipher suite: {}",
+                                        name
+                                    )));
+                                }
+                            }
+                        }
+                        tls.ciphersuites = suites;
                     }
-                    tls.root_store
-                        .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
 
                     if !config.certs_verification {
                         tls.dangerous()
@@ -220,20 +380,45 @@ impl ClientBuilder {
                     }
 
                     Connector::new_rustls_tls(
+                        resolver,
+                        tls,
+                        proxies.clone(),
+                        user_agent(&config.headers),
+                        config.tcp,
+                        config.nodelay,
+                    )?
+                }
+                #[cfg(feature = "__rustls")]
+                TlsBackend::BuiltRustls(mut tls) => {
+                    set_alpn(&mut tls, config.http2_only);
+
+                    Connector::new_rustls_tls(
+                        resolver,
                         tls,
                         proxies.clone(),
                         user_agent(&config.headers),
-                        config.local_address,
+                        config.tcp,
                         config.nodelay,
                     )?
                 }
+                TlsBackend::UnknownPreconfigured => {
+                    return Err(crate::error::builder(
+                        "unknown TLS backend passed to `use_preconfigured_tls`",
+                    ));
+                }
             }
 
             #[cfg(not(feature = "__tls"))]
-            Connector::new(proxies.clone(), config.local_address, config.nodelay)?
+            Connector::new(resolver, proxies.clone(), config.tcp, config.nodelay)?
         };
 
         connector.set_timeout(config.connect_timeout);
+        #[cfg(feature = "__tls")]
+        connector.set_certificate_pins(config.certificate_pins);
+        connector.set_happy_eyeballs_timeout(config.happy_eyeballs_timeout);
+        if let Some(custom) = config.connector {
+            connector.set_transport(custom);
+        }
 
         let mut builder = hyper::Client::builder();
         if config.http2_only {
@@ -247,13 +432,15 @@ impl ClientBuilder {
             builder.http2_initial_connection_window_size(http2_initial_connection_window_size);
         }
 
-        builder.max_idle_per_host(config.max_idle_per_host);
+        builder.pool_idle_timeout(config.pool_idle_timeout);
+        builder.pool_max_idle_per_host(config.pool_max_idle_per_host);
 
         if config.http1_title_case_headers {
             builder.http1_title_case_headers(true);
         }
 
-        let hyper_client = builder.build(connector);
+        let counters = connector.counters();
+        let hyper_client = HyperPool::new(builder, connector);
 
         let proxies_maybe_http_auth = proxies.iter().any(|p| p.maybe_has_http_auth());
 
@@ -261,14 +448,18 @@ impl ClientBuilder {
             inner: Arc::new(ClientRef {
                 #[cfg(feature = "cookies")]
                 cookie_store: config.cookie_store.map(RwLock::new),
-                gzip: config.gzip,
+                accepts: config.accepts,
                 hyper: hyper_client,
                 headers: config.headers,
                 redirect_policy: config.redirect_policy,
                 referer: config.referer,
+                retry_policy: config.retry_policy,
+                middleware: config.middleware,
                 request_timeout: config.timeout,
+                read_timeout: config.read_timeout,
                 proxies,
                 proxies_maybe_http_auth,
+                counters,
             }),
         })
     }
@@ -383,7 +574,8 @@ impl ClientBuilder {
     /// If auto gzip decompresson is turned on:
     ///
     /// - When sending a request and if the request's headers do not already contain
-    ///   an `Accept-Encoding` **and** `Range` values, the `Accept-Encoding` header is set to `gzip`.
+    ///   an `Accept-Encoding` **and** `Range` values, `gzip` is added to the
+    ///   `Accept-Encoding` header, along with any other enabled encoding.
     ///   The request body is **not** automatically compressed.
     /// - When receiving a response, if it's headers contain a `Content-Encoding` value that
     ///   equals to `gzip`, both values `Content-Encoding` and `Content-Length` are removed from the
@@ -396,7 +588,7 @@ impl ClientBuilder {
     /// This requires the optional `gzip` feature to be enabled
     #[cfg(feature = "gzip")]
     pub fn gzip(mut self, enable: bool) -> ClientBuilder {
-        self.config.gzip = enable;
+        self.config.accepts.gzip = enable;
         self
     }
 
@@ -417,6 +609,126 @@ impl ClientBuilder {
         }
     }
 
+    /// Enable auto brotli decompression by checking the `Content-Encoding` response header.
+    ///
+    /// If auto brotli decompression is turned on:
+    ///
+    /// - When sending a request and if the request's headers do not already contain
+    ///   an `Accept-Encoding` **and** `Range` values, `br` is added to the
+    ///   `Accept-Encoding` header, along with any other enabled encoding.
+    ///   The request body is **not** automatically compressed.
+    /// - When receiving a response, if it's headers contain a `Content-Encoding` value that
+    ///   equals to `br`, both values `Content-Encoding` and `Content-Length` are removed from the
+    ///   headers' set. The response body is automatically decompressed.
+    ///
+    /// If the `brotli` feature is turned on, the default option is enabled.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `brotli` feature to be enabled
+    #[cfg(feature = "brotli")]
+    pub fn brotli(mut self, enable: bool) -> ClientBuilder {
+        self.config.accepts.brotli = enable;
+        self
+    }
+
+    /// Disable auto response body brotli decompression.
+    ///
+    /// This method exists even if the optional `brotli` feature is not enabled.
+    /// This can be used to ensure a `Client` doesn't use brotli decompression
+    /// even if another dependency were to enable the optional `brotli` feature.
+    pub fn no_brotli(self) -> ClientBuilder {
+        #[cfg(feature = "brotli")]
+        {
+            self.brotli(false)
+        }
+
+        #[cfg(not(feature = "brotli"))]
+        {
+            self
+        }
+    }
+
+    /// Enable auto deflate decompression by checking the `Content-Encoding` response header.
+    ///
+    /// If auto deflate decompression is turned on:
+    ///
+    /// - When sending a request and if the request's headers do not already contain
+    ///   an `Accept-Encoding` **and** `Range` values, `deflate` is added to the
+    ///   `Accept-Encoding` header, along with any other enabled encoding.
+    ///   The request body is **not** automatically compressed.
+    /// - When receiving a response, if it's headers contain a `Content-Encoding` value that
+    ///   equals to `deflate`, both values `Content-Encoding` and `Content-Length` are removed from the
+    ///   headers' set. The response body is automatically decompressed.
+    ///
+    /// If the `deflate` feature is turned on, the default option is enabled.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `deflate` feature to be enabled
+    #[cfg(feature = "deflate")]
+    pub fn deflate(mut self, enable: bool) -> ClientBuilder {
+        self.config.accepts.deflate = enable;
+        self
+    }
+
+    /// Disable auto response body deflate decompression.
+    ///
+    /// This method exists even if the optional `deflate` feature is not enabled.
+    /// This can be used to ensure a `Client` doesn't use deflate decompression
+    /// even if another dependency were to enable the optional `deflate` feature.
+    pub fn no_deflate(self) -> ClientBuilder {
+        #[cfg(feature = "deflate")]
+        {
+            self.deflate(false)
+        }
+
+        #[cfg(not(feature = "deflate"))]
+        {
+            self
+        }
+    }
+
+    /// Enable auto zstd decompression by checking the `Content-Encoding` response header.
+    ///
+    /// If auto zstd decompression is turned on:
+    ///
+    /// - When sending a request and if the request's headers do not already contain
+    ///   an `Accept-Encoding` **and** `Range` values, `zstd` is added to the
+    ///   `Accept-Encoding` header, along with any other enabled encoding.
+    ///   The request body is **not** automatically compressed.
+    /// - When receiving a response, if it's headers contain a `Content-Encoding` value that
+    ///   equals to `zstd`, both values `Content-Encoding` and `Content-Length` are removed from the
+    ///   headers' set. The response body is automatically decompressed.
+    ///
+    /// If the `zstd` feature is turned on, the default option is enabled.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `zstd` feature to be enabled
+    #[cfg(feature = "zstd")]
+    pub fn zstd(mut self, enable: bool) -> ClientBuilder {
+        self.config.accepts.zstd = enable;
+        self
+    }
+
+    /// Disable auto response body zstd decompression.
+    ///
+    /// This method exists even if the optional `zstd` feature is not enabled.
+    /// This can be used to ensure a `Client` doesn't use zstd decompression
+    /// even if another dependency were to enable the optional `zstd` feature.
+    pub fn no_zstd(self) -> ClientBuilder {
+        #[cfg(feature = "zstd")]
+        {
+            self.zstd(false)
+        }
+
+        #[cfg(not(feature = "zstd"))]
+        {
+            self
+        }
+    }
+
     // Redirect options
 
     /// Set a `RedirectPolicy` for this client.
@@ -435,6 +747,32 @@ impl ClientBuilder {
         self
     }
 
+    // Retry options
+
+    /// Set a `retry::Policy` for this client.
+    ///
+    /// It can be overridden for a single request with
+    /// `RequestBuilder::retry()`.
+    ///
+    /// Default will not retry any request.
+    pub fn retry(mut self, policy: retry::Policy) -> ClientBuilder {
+        self.config.retry_policy = policy;
+        self
+    }
+
+    // Middleware options
+
+    /// Add a `Middleware` to this client.
+    ///
+    /// It is called with every request before it is sent, including the
+    /// requests following redirects, and with every response before it is
+    /// returned. Middleware are called in the order they are added for
+    /// requests, and in the reverse order for responses.
+    pub fn middleware(mut self, middleware: Arc<dyn Middleware>) -> ClientBuilder {
+        self.config.middleware.push(middleware);
+        self
+    }
+
     // Proxy options
 
     /// Add a `Proxy` to the list of proxies the `Client` will use.
@@ -489,14 +827,51 @@ impl ClientBuilder {
         self
     }
 
+    /// Set a timeout for reading the body of a response, which fires when no
+    /// data arrives for `timeout`.
+    ///
+    /// Unlike `timeout`, this doesn't limit slow but steady downloads. It
+    /// starts when the response headers are received, and restarts each
+    /// time data arrives.
+    ///
+    /// Default is `None`.
+    ///
+    /// # Note
+    ///
+    /// This **requires** the futures be executed in a tokio runtime with
+    /// a tokio timer enabled.
+    pub fn read_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.config.read_timeout = Some(timeout);
+        self
+    }
+
     // HTTP options
 
+    /// Set an optional timeout for idle sockets being kept-alive.
+    ///
+    /// Pass `None` to disable timeout.
+    ///
+    /// Default is 90 seconds.
+    pub fn pool_idle_timeout<D>(mut self, val: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.config.pool_idle_timeout = val.into();
+        self
+    }
+
     /// Sets the maximum idle connection per host allowed in the pool.
-    pub fn max_idle_per_host(mut self, max: usize) -> ClientBuilder {
-        self.config.max_idle_per_host = max;
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> ClientBuilder {
+        self.config.pool_max_idle_per_host = max;
         self
     }
 
+    #[doc(hidden)]
+    #[deprecated(note = "renamed to `pool_max_idle_per_host`")]
+    pub fn max_idle_per_host(self, max: usize) -> ClientBuilder {
+        self.pool_max_idle_per_host(max)
+    }
+
     /// Enable case sensitive headers.
     pub fn http1_title_case_headers(mut self) -> ClientBuilder {
         self.config.http1_title_case_headers = true;
@@ -548,7 +923,206 @@ impl ClientBuilder {
     where
         T: Into<Option<IpAddr>>,
     {
-        self.config.local_address = addr.into();
+        self.config.tcp.local_address = addr.into();
+        self
+    }
+
+    /// Set that all sockets send TCP keepalive probes after being idle for
+    /// `interval`.
+    ///
+    /// This helps idle pooled connections survive NAT gateways and firewalls
+    /// that drop silent connections.
+    ///
+    /// Default is `None`.
+    pub fn tcp_keepalive<D>(mut self, interval: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.config.tcp.keepalive = interval.into();
+        self
+    }
+
+    /// Set the size of the send buffer of all sockets (`SO_SNDBUF`).
+    ///
+    /// Default is the operating system's default.
+    pub fn tcp_send_buffer_size(mut self, size: usize) -> ClientBuilder {
+        self.config.tcp.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Set the size of the receive buffer of all sockets (`SO_RCVBUF`).
+    ///
+    /// Default is the operating system's default.
+    pub fn tcp_recv_buffer_size(mut self, size: usize) -> ClientBuilder {
+        self.config.tcp.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Bind all sockets to a network interface, such as `"eth0"`
+    /// (`SO_BINDTODEVICE`).
+    ///
+    /// When set, the addresses of a host are tried in turn, without
+    /// `happy_eyeballs_timeout`. Binding may need the `CAP_NET_RAW`
+    /// capability on older kernels.
+    ///
+    /// This option is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn interface(mut self, interface: &str) -> ClientBuilder {
+        match CString::new(interface) {
+            Ok(interface) => self.config.tcp.interface = Some(interface),
+            Err(e) => self.config.error = Some(crate::error::builder(e)),
+        }
+        self
+    }
+
+    /// Set which IP address families to connect to.
+    ///
+    /// Resolved addresses can be ordered to try one family first, or
+    /// filtered to only use one family. `Response::remote_addr` tells which
+    /// address was connected to.
+    ///
+    /// Default is `AddressFamily::Any`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use reqwest::dns::AddressFamily;
+    /// let client = reqwest::Client::builder()
+    ///     .address_family(AddressFamily::PreferIpv4)
+    ///     .build().unwrap();
+    /// ```
+    pub fn address_family(mut self, family: AddressFamily) -> ClientBuilder {
+        self.config.address_family = family;
+        self
+    }
+
+    /// Set how long to wait for a connection to the preferred address family
+    /// before also trying the other one ("Happy Eyeballs").
+    ///
+    /// When a host resolves to both IPv4 and IPv6 addresses, whichever
+    /// connection is established first is used, so a broken route for one
+    /// family doesn't stall until the connect timeout. Pass `None` to try
+    /// every address in turn instead.
+    ///
+    /// Default is 300 milliseconds.
+    pub fn happy_eyeballs_timeout<D>(mut self, timeout: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.config.happy_eyeballs_timeout = timeout.into();
+        self
+    }
+
+    // Connector options
+
+    /// Establish connections with a custom connector, instead of TCP.
+    ///
+    /// The connector is called with the URI of each destination, or of the
+    /// proxy when one is used, and returns any transport, such as a Unix
+    /// domain socket or an in-memory stream. HTTPS and proxy tunnels are
+    /// still established on top of the connections it returns.
+    ///
+    /// The DNS resolver and the TCP options, such as `local_address` and
+    /// `tcp_nodelay`, only apply to TCP, and are not used. Connections through a SOCKS proxy
+    /// don't use the connector.
+    pub fn connector<C>(mut self, connector: C) -> ClientBuilder
+    where
+        C: Service<Uri> + Clone + Send + Sync + 'static,
+        C::Response: AsyncRead + AsyncWrite + Send + Sync + Unpin + 'static,
+        C::Future: Send + 'static,
+        C::Error: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        self.config.connector = Some(CustomConnector::new(connector));
+        self
+    }
+
+    /// Send every request over the Unix domain socket at `path`, instead of
+    /// TCP.
+    ///
+    /// The URL of a request still sets its `Host` header and path, so the
+    /// host can be any name the server accepts, such as `localhost`.
+    /// Redirects are followed over the same socket.
+    ///
+    /// This replaces any `connector`, and disables the system proxies.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), reqwest::Error> {
+    /// let client = reqwest::Client::builder()
+    ///     .unix_socket("/var/run/docker.sock")
+    ///     .build()?;
+    ///
+    /// let containers = client
+    ///     .get("http://localhost/containers/json")
+    ///     .send()
+    ///     .await?
+    ///     .text()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn unix_socket<P: AsRef<Path>>(mut self, path: P) -> ClientBuilder {
+        self.config.auto_sys_proxy = false;
+        self.connector(UnixConnector::new(path.as_ref()))
+    }
+
+    // DNS options
+
+    /// Override the DNS resolver used by this `Client`.
+    ///
+    /// The resolver is used to look up the addresses of every destination
+    /// and proxy the `Client` connects to.
+    ///
+    /// Default is the system's `getaddrinfo`, run on a blocking threadpool.
+    pub fn dns_resolver(mut self, resolver: Arc<dyn Resolve>) -> ClientBuilder {
+        self.config.dns_resolver = Some(resolver);
+        self
+    }
+
+    /// Override DNS resolution for specific domains to a particular IP address.
+    ///
+    /// The URL is left untouched, so the `Host` header, TLS server name and
+    /// certificate validation all still use `domain`. Overrides also apply
+    /// to redirects and proxies that use the same domain.
+    ///
+    /// # Warning
+    ///
+    /// Since the DNS protocol has no notion of ports, if you wish to send
+    /// traffic to a particular port you must include this port in the URL
+    /// itself, any port in the overridden addr will be ignored and traffic sent
+    /// to the conventional port for the given scheme (e.g. 80 for http).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::net::SocketAddr;
+    /// let addr: SocketAddr = "10.0.0.2:443".parse().unwrap();
+    /// let client = reqwest::Client::builder()
+    ///     .resolve("api.example.com", addr)
+    ///     .build()
+    ///     .unwrap();
+    /// # drop(client);
+    /// ```
+    pub fn resolve(self, domain: &str, addr: SocketAddr) -> ClientBuilder {
+        self.resolve_to_addrs(domain, &[addr])
+    }
+
+    /// Override DNS resolution for specific domains to particular IP addresses.
+    ///
+    /// The addresses are tried in the order given.
+    ///
+    /// # Warning
+    ///
+    /// Since the DNS protocol has no notion of ports, if you wish to send
+    /// traffic to a particular port you must include this port in the URL
+    /// itself, any port in the overridden addresses will be ignored and traffic sent
+    /// to the conventional port for the given scheme (e.g. 80 for http).
+    pub fn resolve_to_addrs(mut self, domain: &str, addrs: &[SocketAddr]) -> ClientBuilder {
+        self.config
+            .dns_overrides
+            .insert(domain.to_ascii_lowercase(), addrs.to_vec());
         self
     }
 
@@ -569,6 +1143,115 @@ impl ClientBuilder {
         self
     }
 
+    /// Controls the use of built-in root certificates during certificate
+    /// validation.
+    ///
+    /// These are the system's trust store with native-tls. With rustls, they
+    /// are the bundled `webpki-roots` and, with the `rustls-tls-native-roots`
+    /// feature, the system's trust store. When disabled, only the certificates
+    /// added with `add_root_certificate` are trusted.
+    ///
+    /// Defaults to `true`.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn tls_built_in_root_certs(mut self, tls_built_in_root_certs: bool) -> ClientBuilder {
+        self.config.tls_built_in_root_certs = tls_built_in_root_certs;
+        self
+    }
+
+    /// Pin the public key of the certificate of `host`.
+    ///
+    /// `sha256_spki` is the SHA-256 hash of the DER `SubjectPublicKeyInfo`
+    /// of the certificate the server presents for itself, as printed by:
+    ///
+    /// ```bash
+    /// openssl x509 -in cert.pem -pubkey -noout | openssl pkey -pubin -outform der | openssl dgst -sha256
+    /// ```
+    ///
+    /// Once a host is pinned, its TLS connections are only kept if the
+    /// certificate matches one of its pins, so pinning a backup key as well
+    /// allows rotating keys. This is checked after the usual certificate
+    /// validation, and a mismatch fails the request with an error for which
+    /// `Error::is_certificate_pin` is true.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn doc() -> Result<(), reqwest::Error> {
+    /// let pin = [0x7a; 32];
+    /// let client = reqwest::Client::builder()
+    ///     .pin_certificate("payments.example.com", pin)
+    ///     .build()?;
+    /// # drop(client);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn pin_certificate(mut self, host: &str, sha256_spki: [u8; 32]) -> ClientBuilder {
+        self.config.certificate_pins.add(host, sha256_spki);
+        self
+    }
+
+    /// Set the minimum version of TLS to negotiate.
+    ///
+    /// By default, the minimum is the one of the TLS backend. native-tls
+    /// can't require TLS 1.3, and rustls only supports TLS 1.2 and 1.3;
+    /// `build` fails when the backend can't honor the setting.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn min_tls_version(mut self, version: TlsVersion) -> ClientBuilder {
+        self.config.min_tls_version = Some(version);
+        self
+    }
+
+    /// Set the maximum version of TLS to negotiate.
+    ///
+    /// By default, the maximum is the one of the TLS backend. Since rustls
+    /// only supports TLS 1.2 and 1.3, `build` fails with a maximum below
+    /// TLS 1.2 when using rustls.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn max_tls_version(mut self, version: TlsVersion) -> ClientBuilder {
+        self.config.max_tls_version = Some(version);
+        self
+    }
+
+    /// Restrict the cipher suites offered to the server, in order of
+    /// preference.
+    ///
+    /// Suites are named as registered with IANA, such as
+    /// `TLS_AES_128_GCM_SHA256` or `TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384`.
+    ///
+    /// Only rustls supports selecting cipher suites; `build` fails with
+    /// native-tls, or when rustls doesn't support one of the suites.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn tls_cipher_suites(mut self, suites: &[&str]) -> ClientBuilder {
+        self.config.tls_cipher_suites = Some(suites.iter().map(|&s| s.to_owned()).collect());
+        self
+    }
+
     /// Sets the identity to be used for client certificate authentication.
     ///
     /// # Optional
@@ -649,15 +1332,89 @@ impl ClientBuilder {
     /// # Optional
     ///
     /// This requires the optional `rustls-tls` feature to be enabled.
-    #[cfg(feature = "rustls-tls")]
+    #[cfg(feature = "__rustls")]
     pub fn use_rustls_tls(mut self) -> ClientBuilder {
         self.config.tls = TlsBackend::Rustls;
         self
     }
+
+    /// Use a preconfigured TLS backend.
+    ///
+    /// `tls` is either a `native_tls::TlsConnector` or a
+    /// `rustls::ClientConfig`, from the same versions of those crates as
+    /// reqwest, and the `Client` uses it as given. The other TLS options of
+    /// this builder, such as `add_root_certificate` or
+    /// `danger_accept_invalid_certs`, are ignored, except
+    /// `pin_certificate`. With rustls, the ALPN protocols are still set
+    /// by reqwest to negotiate HTTP/2.
+    ///
+    /// `build` fails if `tls` is of any other type, or if the feature of
+    /// its backend isn't enabled.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn use_preconfigured_tls(mut self, tls: impl Any) -> ClientBuilder {
+        let mut tls = Some(tls);
+        #[cfg(feature = "default-tls")]
+        {
+            if let Some(conn) =
+                (&mut tls as &mut dyn Any).downcast_mut::<Option<native_tls_crate::TlsConnector>>()
+            {
+                let tls = conn.take().expect("is definitely Some");
+                self.config.tls = TlsBackend::BuiltNativeTls(tls);
+                return self;
+            }
+        }
+        #[cfg(feature = "__rustls")]
+        {
+            if let Some(conn) =
+                (&mut tls as &mut dyn Any).downcast_mut::<Option<rustls::ClientConfig>>()
+            {
+                let tls = conn.take().expect("is definitely Some");
+                self.config.tls = TlsBackend::BuiltRustls(tls);
+                return self;
+            }
+        }
+
+        self.config.tls = TlsBackend::UnknownPreconfigured;
+        self
+    }
 }
 
 type HyperClient = hyper::Client<Connector, super::body::ImplStream>;
 
+/// The hyper `Client` and its connection pool, which can be replaced by a
+/// new one to close the pooled connections.
+struct HyperPool {
+    builder: hyper::client::Builder,
+    connector: Connector,
+    client: RwLock<HyperClient>,
+}
+
+impl HyperPool {
+    fn new(builder: hyper::client::Builder, connector: Connector) -> HyperPool {
+        let client = builder.build(connector.clone());
+        HyperPool {
+            builder,
+            connector,
+            client: RwLock::new(client),
+        }
+    }
+
+    fn request(&self, req: hyper::Request<super::body::ImplStream>) -> ResponseFuture {
+        self.client.read().unwrap().request(req)
+    }
+
+    fn reset(&self) {
+        let client = self.builder.build(self.connector.clone());
+        // Drop the old client once the lock is released.
+        let _old = std::mem::replace(&mut *self.client.write().unwrap(), client);
+    }
+}
+
 impl Default for Client {
     fn default() -> Self {
         Self::new()
@@ -771,13 +1528,56 @@ impl Client {
         self.execute_request(request)
     }
 
-    pub(super) fn execute_request(&self, req: Request) -> Pending {
-        let (method, url, mut headers, body, timeout) = req.pieces();
+    /// Closes the idle connections in the pool.
+    ///
+    /// Connections in use by a response are closed once that response is
+    /// done, instead of being returned to the pool. Later requests open new
+    /// connections.
+    ///
+    /// This is useful when the servers behind a host change, such as on a
+    /// configuration reload, so that requests stop reusing connections to
+    /// the old ones.
+    pub fn close_idle_connections(&self) {
+        self.inner.hyper.reset();
+    }
+
+    /// Returns a snapshot of the statistics of this `Client`.
+    ///
+    /// The statistics are shared with the clones of this `Client`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), reqwest::Error> {
+    /// let client = reqwest::Client::new();
+    /// client.get("http://httpbin.org/get").send().await?;
+    ///
+    /// let stats = client.stats();
+    /// println!("{} connections opened", stats.connections_created());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> Stats {
+        self.inner.counters.snapshot()
+    }
+
+    pub(super) fn execute_request(&self, mut req: Request) -> Pending {
+        #[cfg(any(
+            feature = "brotli",
+            feature = "gzip",
+            feature = "deflate",
+            feature = "zstd"
+        ))]
+        {
+            if let Err(err) = req.compress_body() {
+                return Pending::new_err(err);
+            }
+        }
 
         // insert default headers in the request headers
         // without overwriting already appended headers.
         for (key, value) in &self.inner.headers {
-            if let Entry::Vacant(entry) = headers.entry(key) {
+            if let Entry::Vacant(entry) = req.headers_mut().entry(key) {
                 entry.insert(value.clone());
             }
         }
@@ -786,18 +1586,27 @@ impl Client {
         #[cfg(feature = "cookies")]
         {
             if let Some(cookie_store_wrapper) = self.inner.cookie_store.as_ref() {
-                if headers.get(crate::header::COOKIE).is_none() {
+                if req.headers().get(crate::header::COOKIE).is_none() {
                     let cookie_store = cookie_store_wrapper.read().unwrap();
-                    add_cookie_header(&mut headers, &cookie_store, &url);
+                    let url = req.url().clone();
+                    add_cookie_header(req.headers_mut(), &cookie_store, &url);
                 }
             }
         }
 
-        if self.inner.gzip && !headers.contains_key(ACCEPT_ENCODING) && !headers.contains_key(RANGE)
-        {
-            headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        if let Some(accept_encoding) = self.inner.accepts.as_str() {
+            let headers = req.headers_mut();
+            if !headers.contains_key(ACCEPT_ENCODING) && !headers.contains_key(RANGE) {
+                headers.insert(ACCEPT_ENCODING, HeaderValue::from_static(accept_encoding));
+            }
+        }
+
+        if let Some(res) = middleware::on_request(&self.inner.middleware, &mut req) {
+            return Pending::new_response(res);
         }
 
+        let (method, url, mut headers, body, timeout, retry) = req.pieces();
+
         let uri = expect_uri(&url);
 
         let (reusable, body) = match body {
@@ -824,6 +1633,8 @@ impl Client {
         *req.headers_mut() = headers.clone();
 
         let in_flight = self.inner.hyper.request(req);
+        let in_flight_count = Some(InFlight::new(self.inner.counters.clone()));
+        let sent_at = Instant::now();
 
         Pending {
             inner: PendingInner::Request(PendingRequest {
@@ -833,11 +1644,19 @@ impl Client {
                 body: reusable,
 
                 urls: Vec::new(),
+                redirect_timings: Vec::new(),
+
+                retry,
+                retries: 0,
+                proxy_challenged: false,
 
                 client: self.inner.clone(),
 
                 in_flight,
+                in_flight_count,
+                sent_at,
                 timeout,
+                retry_delay: None,
             }),
         }
     }
@@ -870,6 +1689,34 @@ impl Client {
     }
 }
 
+impl Service<Request> for Client {
+    type Response = Response;
+    type Error = crate::Error;
+    type Future = Pending;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        self.execute_request(req)
+    }
+}
+
+impl Service<Request> for &'_ Client {
+    type Response = Response;
+    type Error = crate::Error;
+    type Future = Pending;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        self.execute_request(req)
+    }
+}
+
 impl fmt::Debug for Client {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut builder = f.debug_struct("Client");
@@ -898,7 +1745,7 @@ impl Config {
             }
         }
 
-        f.field("gzip", &self.gzip);
+        f.field("accepts", &self.accepts);
 
         if !self.proxies.is_empty() {
             f.field("proxies", &self.proxies);
@@ -912,6 +1759,14 @@ impl Config {
             f.field("referer", &true);
         }
 
+        if !self.retry_policy.is_default() {
+            f.field("retry_policy", &self.retry_policy);
+        }
+
+        if !self.middleware.is_empty() {
+            f.field("middleware", &self.middleware.len());
+        }
+
         f.field("default_headers", &self.headers);
 
         if self.http1_title_case_headers {
@@ -926,18 +1781,57 @@ impl Config {
             f.field("connect_timeout", d);
         }
 
+        if let Some(ref d) = self.read_timeout {
+            f.field("read_timeout", d);
+        }
+
         if let Some(ref d) = self.timeout {
             f.field("timeout", d);
         }
 
-        if let Some(ref v) = self.local_address {
+        if let Some(ref v) = self.tcp.local_address {
             f.field("local_address", v);
         }
 
+        if let Some(ref v) = self.tcp.keepalive {
+            f.field("tcp_keepalive", v);
+        }
+
+        if let Some(ref v) = self.tcp.send_buffer_size {
+            f.field("tcp_send_buffer_size", v);
+        }
+
+        if let Some(ref v) = self.tcp.recv_buffer_size {
+            f.field("tcp_recv_buffer_size", v);
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(ref v) = self.tcp.interface {
+                f.field("interface", v);
+            }
+        }
+
         if self.nodelay {
             f.field("tcp_nodelay", &true);
         }
 
+        if self.address_family != AddressFamily::Any {
+            f.field("address_family", &self.address_family);
+        }
+
+        if self.connector.is_some() {
+            f.field("connector", &true);
+        }
+
+        if self.dns_resolver.is_some() {
+            f.field("dns_resolver", &true);
+        }
+
+        if !self.dns_overrides.is_empty() {
+            f.field("dns_overrides", &self.dns_overrides);
+        }
+
         #[cfg(feature = "native-tls")]
         {
             if !self.hostname_verification {
@@ -950,9 +1844,29 @@ impl Config {
             if !self.certs_verification {
                 f.field("danger_accept_invalid_certs", &true);
             }
+
+            if !self.tls_built_in_root_certs {
+                f.field("tls_built_in_root_certs", &false);
+            }
+
+            if !self.certificate_pins.is_empty() {
+                f.field("certificate_pins", &self.certificate_pins);
+            }
+
+            if let Some(ref min) = self.min_tls_version {
+                f.field("min_tls_version", min);
+            }
+
+            if let Some(ref max) = self.max_tls_version {
+                f.field("max_tls_version", max);
+            }
+
+            if let Some(ref suites) = self.tls_cipher_suites {
+                f.field("tls_cipher_suites", suites);
+            }
         }
 
-        #[cfg(all(feature = "native-tls-crate", feature = "rustls-tls"))]
+        #[cfg(all(feature = "native-tls-crate", feature = "__rustls"))]
         {
             f.field("tls_backend", &self.tls);
         }
@@ -962,17 +1876,34 @@ impl Config {
 struct ClientRef {
     #[cfg(feature = "cookies")]
     cookie_store: Option<RwLock<cookie::CookieStore>>,
-    gzip: bool,
+    accepts: Accepts,
     headers: HeaderMap,
-    hyper: HyperClient,
+    hyper: HyperPool,
     redirect_policy: redirect::Policy,
     referer: bool,
+    retry_policy: retry::Policy,
+    middleware: Vec<Arc<dyn Middleware>>,
     request_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
     proxies: Arc<Vec<Proxy>>,
     proxies_maybe_http_auth: bool,
+    counters: Arc<Counters>,
 }
 
 impl ClientRef {
+    /// Answers the `407` challenge of the proxy that a plain `http` request
+    /// was sent through.
+    fn proxy_challenge(&self, method: &Method, url: &Url, headers: &HeaderMap) -> Option<HeaderValue> {
+        let dst = expect_uri(url);
+        if dst.scheme() != Some(&Scheme::HTTP) {
+            return None;
+        }
+
+        let proxy = self.proxies.iter().find(|proxy| proxy.is_match(&dst))?;
+        let challenge = ProxyChallenge::new(method.clone(), dst.to_string(), headers);
+        proxy.challenge_handler()?.answer(&challenge)
+    }
+
     fn fmt_fields(&self, f: &mut fmt::DebugStruct<'_, '_>) {
         // Instead of deriving Debug, only print fields when their output
         // would provide relevant or interesting data.
@@ -984,7 +1915,7 @@ impl ClientRef {
             }
         }
 
-        f.field("gzip", &self.gzip);
+        f.field("accepts", &self.accepts);
 
         if !self.proxies.is_empty() {
             f.field("proxies", &self.proxies);
@@ -998,23 +1929,38 @@ impl ClientRef {
             f.field("referer", &true);
         }
 
+        if !self.retry_policy.is_default() {
+            f.field("retry_policy", &self.retry_policy);
+        }
+
+        if !self.middleware.is_empty() {
+            f.field("middleware", &self.middleware.len());
+        }
+
         f.field("default_headers", &self.headers);
 
 
         if let Some(ref d) = self.request_timeout {
             f.field("timeout", d);
         }
+
+        if let Some(ref d) = self.read_timeout {
+            f.field("read_timeout", d);
+        }
     }
 }
 
 
 
-pub(super) struct Pending {
+/// A `Future` that will resolve to a `Response`, returned by the `Service`
+/// implementation of `Client`.
+pub struct Pending {
     inner: PendingInner,
 }
 
 enum PendingInner {
     Request(PendingRequest),
+    Response(Option<Response>),
     Error(Option<crate::Error>),
 }
 
@@ -1025,11 +1971,19 @@ struct PendingRequest {
     body: Option<Option<Bytes>>,
 
     urls: Vec<Url>,
+    redirect_timings: Vec<Timings>,
+
+    retry: Option<retry::Policy>,
+    retries: usize,
+    proxy_challenged: bool,
 
     client: Arc<ClientRef>,
 
     in_flight: ResponseFuture,
+    in_flight_count: Option<InFlight>,
+    sent_at: Instant,
     timeout: Option<Delay>,
+    retry_delay: Option<Delay>,
 }
 
 impl PendingRequest {
@@ -1041,6 +1995,10 @@ impl PendingRequest {
         unsafe { Pin::map_unchecked_mut(self, |x| &mut x.timeout) }
     }
 
+    fn retry_delay(self: Pin<&mut Self>) -> Pin<&mut Option<Delay>> {
+        unsafe { Pin::map_unchecked_mut(self, |x| &mut x.retry_delay) }
+    }
+
     fn urls(self: Pin<&mut Self>) -> &mut Vec<Url> {
         unsafe { &mut Pin::get_unchecked_mut(self).urls }
     }
@@ -1048,6 +2006,67 @@ impl PendingRequest {
     fn headers(self: Pin<&mut Self>) -> &mut HeaderMap {
         unsafe { &mut Pin::get_unchecked_mut(self).headers }
     }
+
+    fn retry_policy(&self) -> &retry::Policy {
+        self.retry.as_ref().unwrap_or(&self.client.retry_policy)
+    }
+
+    fn retry_error(&self, err: &hyper::Error) -> Option<Duration> {
+        // A streaming body has already been consumed, it can't be sent again.
+        if let Some(None) = self.body {
+            return None;
+        }
+        self.retry_policy()
+            .retry_error(&self.method, self.retries, err)
+    }
+
+    fn retry_response(&self, res: &hyper::Response<hyper::Body>) -> Option<Duration> {
+        if let Some(None) = self.body {
+            return None;
+        }
+        self.retry_policy()
+            .retry_response(&self.method, self.retries, res.status(), res.headers())
+    }
+
+    fn proxy_challenge(&self, res: &hyper::Response<hyper::Body>) -> Option<HeaderValue> {
+        if res.status() != StatusCode::PROXY_AUTHENTICATION_REQUIRED || self.proxy_challenged {
+            return None;
+        }
+        if let Some(None) = self.body {
+            return None;
+        }
+        self.client
+            .proxy_challenge(&self.method, &self.url, res.headers())
+    }
+
+    fn schedule_retry(mut self: Pin<&mut Self>, delay: Duration) {
+        self.retries += 1;
+        debug!(
+            "retrying {:?} '{}' in {:?} (retry {})",
+            self.method, self.url, delay, self.retries
+        );
+        self.as_mut()
+            .retry_delay()
+            .set(Some(tokio::time::delay_for(delay)));
+    }
+
+    fn resend(mut self: Pin<&mut Self>) {
+        let uri = expect_uri(&self.url);
+        let body = match self.body {
+            Some(Some(ref body)) => Body::reusable(body.clone()),
+            _ => Body::empty(),
+        };
+        let mut req = hyper::Request::builder()
+            .method(self.method.clone())
+            .uri(uri)
+            .body(body.into_stream())
+            .expect("valid request parts");
+
+        *req.headers_mut() = self.headers.clone();
+        *self.as_mut().in_flight().get_mut() = self.client.hyper.request(req);
+        self.in_flight_count = Some(InFlight::new(self.client.counters.clone()));
+        self.sent_at = Instant::now();
+    }
 }
 
 impl Pending {
@@ -1057,6 +2076,12 @@ impl Pending {
         }
     }
 
+    fn new_response(res: Response) -> Pending {
+        Pending {
+            inner: PendingInner::Response(Some(res)),
+        }
+    }
+
     fn inner(self: Pin<&mut Self>) -> Pin<&mut PendingInner> {
         unsafe { Pin::map_unchecked_mut(self, |x| &mut x.inner) }
     }
@@ -1069,6 +2094,9 @@ impl Future for Pending {
         let inner = self.inner();
         match inner.get_mut() {
             PendingInner::Request(ref mut req) => Pin::new(req).poll(cx),
+            PendingInner::Response(ref mut res) => Poll::Ready(Ok(res
+                .take()
+                .expect("Pending response polled more than once"))),
             PendingInner::Error(ref mut err) => Poll::Ready(Err(err
                 .take()
                 .expect("Pending error polled more than once"))),
@@ -1089,13 +2117,43 @@ impl Future for PendingRequest {
         }
 
         loop {
+            if let Some(delay) = self.as_mut().retry_delay().as_mut().as_pin_mut() {
+                match delay.poll(cx) {
+                    Poll::Ready(()) => (),
+                    Poll::Pending => return Poll::Pending,
+                }
+                self.as_mut().retry_delay().set(None);
+                self.as_mut().resend();
+            }
+
             let res = match self.as_mut().in_flight().as_mut().poll(cx) {
-                Poll::Ready(Err(e)) => {
-                    return Poll::Ready(Err(crate::error::request(e).with_url(self.url.clone())));
+                Poll::Ready(res) => {
+                    self.in_flight_count = None;
+                    res
                 }
-                Poll::Ready(Ok(res)) => res,
                 Poll::Pending => return Poll::Pending,
             };
+            let mut res = match res {
+                Err(e) => {
+                    if let Some(delay) = self.retry_error(&e) {
+                        self.as_mut().schedule_retry(delay);
+                        continue;
+                    }
+                    return Poll::Ready(Err(crate::error::from_hyper(e).with_url(self.url.clone())));
+                }
+                Ok(res) => res,
+            };
+            let conn = match res.extensions_mut().remove::<ConnInfo>() {
+                Some(conn) => {
+                    let conn = conn.first_use();
+                    if conn.is_none() {
+                        self.client.counters.connection_reused();
+                    }
+                    conn
+                }
+                None => None,
+            };
+            let mut timings = Timings::new(self.sent_at.elapsed(), conn);
 
             #[cfg(feature = "cookies")]
             {
@@ -1107,6 +2165,17 @@ impl Future for PendingRequest {
                     store.0.store_response_cookies(cookies, &self.url);
                 }
             }
+            if let Some(delay) = self.retry_response(&res) {
+                self.as_mut().schedule_retry(delay);
+                continue;
+            }
+            if let Some(auth) = self.proxy_challenge(&res) {
+                debug!("answering proxy challenge for '{}'", self.url);
+                self.proxy_challenged = true;
+                self.headers.insert(PROXY_AUTHORIZATION, auth);
+                self.as_mut().resend();
+                continue;
+            }
             let should_redirect = match res.status() {
                 StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND | StatusCode::SEE_OTHER => {
                     self.body = None;
@@ -1175,6 +2244,9 @@ impl Future for PendingRequest {
 
                     match action {
                         redirect::ActionKind::Follow => {
+                            self.client.counters.redirect_followed();
+                            self.redirect_timings.push(timings);
+                            self.proxy_challenged = false;
                             self.url = loc;
 
                             let mut headers =
@@ -1182,16 +2254,6 @@ impl Future for PendingRequest {
 
                             remove_sensitive_headers(&mut headers, &self.url, &self.urls);
                             debug!("redirecting to {:?} '{}'", self.method, self.url);
-                            let uri = expect_uri(&self.url);
-                            let body = match self.body {
-                                Some(Some(ref body)) => Body::reusable(body.clone()),
-                                _ => Body::empty(),
-                            };
-                            let mut req = hyper::Request::builder()
-                                .method(self.method.clone())
-                                .uri(uri.clone())
-                                .body(body.into_stream())
-                                .expect("valid request parts");
 
                             // Add cookies from the cookie store.
                             #[cfg(feature = "cookies")]
@@ -1204,9 +2266,45 @@ impl Future for PendingRequest {
                                 }
                             }
 
+                            let mut next = Request::new(self.method.clone(), self.url.clone());
+                            *next.headers_mut() = headers;
+                            *next.body_mut() = match self.body {
+                                Some(Some(ref body)) => Some(Body::reusable(body.clone())),
+                                _ => None,
+                            };
+
+                            if let Some(res) =
+                                middleware::on_request(&self.client.middleware, &mut next)
+                            {
+                                return Poll::Ready(Ok(res));
+                            }
+
+                            // A middleware may have changed any part of the request.
+                            let (method, url, headers, body, _, _) = next.pieces();
+                            let uri = expect_uri(&url);
+                            let (reusable, body) = match body {
+                                Some(body) => {
+                                    let (reusable, body) = body.try_reuse();
+                                    (Some(reusable), body)
+                                }
+                                None => (None, Body::empty()),
+                            };
+                            self.method = method;
+                            self.url = url;
+                            self.body = reusable;
+
+                            let mut req = hyper::Request::builder()
+                                .method(self.method.clone())
+                                .uri(uri)
+                                .body(body.into_stream())
+                                .expect("valid request parts");
+
                             *req.headers_mut() = headers.clone();
-                            std::mem::swap(self.as_mut().headers(), &mut headers);
+                            *self.as_mut().headers() = headers;
                             *self.as_mut().in_flight().get_mut() = self.client.hyper.request(req);
+                            self.in_flight_count =
+                                Some(InFlight::new(self.client.counters.clone()));
+                            self.sent_at = Instant::now();
                             continue;
                         }
                         redirect::ActionKind::Stop => {
@@ -1221,7 +2319,16 @@ impl Future for PendingRequest {
                     }
                 }
             }
-            let res = Response::new(res, self.url.clone(), self.client.gzip, self.timeout.take());
+            timings.set_redirects(std::mem::take(&mut self.redirect_timings));
+            let mut res = Response::new(
+                res,
+                self.url.clone(),
+                self.client.accepts,
+                self.timeout.take(),
+                self.client.read_timeout,
+                timings,
+            );
+            middleware::on_response(&self.client.middleware, &mut res);
             return Poll::Ready(Ok(res));
         }
     }
@@ -1235,11 +2342,26 @@ impl fmt::Debug for Pending {
                 .field("method", &req.method)
                 .field("url", &req.url)
                 .finish(),
+            PendingInner::Response(ref res) => {
+                f.debug_struct("Pending").field("response", res).finish()
+            }
             PendingInner::Error(ref err) => f.debug_struct("Pending").field("error", err).finish(),
         }
     }
 }
 
+#[cfg(not(feature = "trust-dns"))]
+fn default_resolver() -> crate::Result<Arc<dyn Resolve>> {
+    Ok(Arc::new(GaiResolver::new()))
+}
+
+#[cfg(feature = "trust-dns")]
+fn default_resolver() -> crate::Result<Arc<dyn Resolve>> {
+    TrustDnsResolver::new()
+        .map(|resolver| Arc::new(resolver) as Arc<dyn Resolve>)
+        .map_err(crate::error::builder)
+}
+
 fn make_referer(next: &Url, previous: &Url) -> Option<HeaderValue> {
     if next.scheme() == "http" && previous.scheme() == "https" {
         return None;
@@ -1,229 +1,490 @@
-pub(crate) use self::imp::Decoder;
+use std::fmt;
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
-#[cfg(not(feature = "gzip"))]
-mod imp {
-    use std::pin::Pin;
-    use std::task::{Context, Poll};
+#[cfg(feature = "brotli")]
+use async_compression::stream::BrotliDecoder;
+#[cfg(feature = "gzip")]
+use async_compression::stream::GzipDecoder;
+#[cfg(feature = "deflate")]
+use async_compression::stream::ZlibDecoder;
+#[cfg(feature = "zstd")]
+use async_compression::stream::ZstdDecoder;
+use bytes::Bytes;
+use futures_core::Stream;
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+use futures_util::stream::Peekable;
+use http::HeaderMap;
+
+use super::super::Body;
+use crate::error;
+
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Accepts {
+    #[cfg(feature = "gzip")]
+    pub(super) gzip: bool,
+    #[cfg(feature = "brotli")]
+    pub(super) brotli: bool,
+    #[cfg(feature = "deflate")]
+    pub(super) deflate: bool,
+    #[cfg(feature = "zstd")]
+    pub(super) zstd: bool,
+}
+
+/// A response decompressor over a non-blocking stream of chunks.
+///
+/// The inner decoder may be constructed asynchronously.
+pub(crate) struct Decoder {
+    inner: Inner,
+}
+
+enum Inner {
+    /// A `PlainText` decoder just returns the response content as is.
+    PlainText(super::body::ImplStream),
+    /// A `Gzip` decoder will uncompress the gzipped response content before returning it.
+    #[cfg(feature = "gzip")]
+    Gzip(GzipDecoder<Peekable<IoStream>>),
+    /// A `Brotli` decoder will uncompress the brotlied response content before returning it.
+    #[cfg(feature = "brotli")]
+    Brotli(BrotliDecoder<Peekable<IoStream>>),
+    /// A `Deflate` decoder will uncompress the deflated response content before returning it.
+    #[cfg(feature = "deflate")]
+    Deflate(ZlibDecoder<Peekable<IoStream>>),
+    /// A `Zstd` decoder will uncompress the zstd compressed response content before returning it.
+    #[cfg(feature = "zstd")]
+    Zstd(ZstdDecoder<Peekable<IoStream>>),
+    /// A decoder that doesn't have a value yet.
+    #[cfg(any(
+        feature = "brotli",
+        feature = "gzip",
+        feature = "deflate",
+        feature = "zstd"
+    ))]
+    Pending(Pending),
+}
+
+/// A future attempt to poll the response body for EOF so we know whether to use a decoder or not.
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+struct Pending(Peekable<IoStream>, DecoderType);
+
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+struct IoStream(super::body::ImplStream);
 
-    use bytes::Bytes;
-    use futures_core::Stream;
-    use http::HeaderMap;
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+enum DecoderType {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "brotli")]
+    Brotli,
+    #[cfg(feature = "deflate")]
+    Deflate,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
 
-    use super::super::Body;
-    pub(crate) struct Decoder {
-        inner: super::super::body::ImplStream,
+impl fmt::Debug for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Decoder").finish()
     }
+}
 
-    impl Decoder {
-        #[cfg(feature = "blocking")]
-        pub(crate) fn empty() -> Decoder {
-            Decoder::plain_text(Body::empty())
+impl Decoder {
+    #[cfg(feature = "blocking")]
+    pub(crate) fn empty() -> Decoder {
+        Decoder {
+            inner: Inner::PlainText(Body::empty().into_stream()),
         }
+    }
 
-        /// A plain text decoder.
-        ///
-        /// This decoder will emit the underlying chunks as-is.
-        fn plain_text(body: Body) -> Decoder {
-            Decoder {
-                inner: body.into_stream(),
-            }
+    /// A plain text decoder.
+    ///
+    /// This decoder will emit the underlying chunks as-is.
+    fn plain_text(body: Body) -> Decoder {
+        Decoder {
+            inner: Inner::PlainText(body.into_stream()),
         }
+    }
+
+    /// A gzip decoder.
+    ///
+    /// This decoder will buffer and decompress chunks that are gzipped.
+    #[cfg(feature = "gzip")]
+    fn gzip(body: Body) -> Decoder {
+        use futures_util::StreamExt;
 
-        pub(crate) fn detect(_: &mut HeaderMap, body: Body, _: bool) -> Decoder {
-            Decoder::plain_text(body)
+        Decoder {
+            inner: Inner::Pending(Pending(
+                IoStream(body.into_stream()).peekable(),
+                DecoderType::Gzip,
+            )),
         }
     }
 
-    impl Stream for Decoder {
-        type Item = crate::Result<Bytes>;
+    /// A brotli decoder.
+    ///
+    /// This decoder will buffer and decompress chunks that are brotlied.
+    #[cfg(feature = "brotli")]
+    fn brotli(body: Body) -> Decoder {
+        use futures_util::StreamExt;
 
-        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-            Pin::new(&mut self.inner).poll_next(cx)
+        Decoder {
+            inner: Inner::Pending(Pending(
+                IoStream(body.into_stream()).peekable(),
+                DecoderType::Brotli,
+            )),
         }
     }
-}
 
-#[cfg(feature = "gzip")]
-mod imp {
-    use std::future::Future;
-    use std::pin::Pin;
-    use std::task::{Context, Poll};
-    use std::{fmt, mem};
-
-    use async_compression::stream::GzipDecoder;
-    use bytes::Bytes;
-    use futures_core::Stream;
-    use futures_util::stream::Peekable;
-    use http::header::{CONTENT_ENCODING, CONTENT_LENGTH, TRANSFER_ENCODING};
-    use http::HeaderMap;
-    use log::warn;
-
-    use super::super::Body;
-    use crate::error;
-
-    /// A response decompressor over a non-blocking stream of chunks.
+    /// A deflate decoder.
     ///
-    /// The inner decoder may be constructed asynchronously.
-    pub(crate) struct Decoder {
-        inner: Inner,
-    }
+    /// This decoder will buffer and decompress chunks that are deflated.
+    #[cfg(feature = "deflate")]
+    fn deflate(body: Body) -> Decoder {
+        use futures_util::StreamExt;
 
-    enum Inner {
-        /// A `PlainText` decoder just returns the response content as is.
-        PlainText(super::super::body::ImplStream),
-        /// A `Gzip` decoder will uncompress the gzipped response content before returning it.
-        Gzip(GzipDecoder<Peekable<IoStream>>),
-        /// A decoder that doesn't have a value yet.
-        Pending(Pending),
+        Decoder {
+            inner: Inner::Pending(Pending(
+                IoStream(body.into_stream()).peekable(),
+                DecoderType::Deflate,
+            )),
+        }
     }
 
-    /// A future attempt to poll the response body for EOF so we know whether to use gzip or not.
-    struct Pending(Peekable<IoStream>);
-
-    struct IoStream(super::super::body::ImplStream);
+    /// A zstd decoder.
+    ///
+    /// This decoder will buffer and decompress chunks that are zstd compressed.
+    #[cfg(feature = "zstd")]
+    fn zstd(body: Body) -> Decoder {
+        use futures_util::StreamExt;
 
-    impl fmt::Debug for Decoder {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            f.debug_struct("Decoder").finish()
+        Decoder {
+            inner: Inner::Pending(Pending(
+                IoStream(body.into_stream()).peekable(),
+                DecoderType::Zstd,
+            )),
         }
     }
 
-    impl Decoder {
-        #[cfg(feature = "blocking")]
-        pub(crate) fn empty() -> Decoder {
-            Decoder {
-                inner: Inner::PlainText(Body::empty().into_stream()),
+    #[cfg(any(
+        feature = "brotli",
+        feature = "gzip",
+        feature = "deflate",
+        feature = "zstd"
+    ))]
+    fn detect_encoding(headers: &mut HeaderMap, encoding_str: &str) -> bool {
+        use http::header::{CONTENT_ENCODING, CONTENT_LENGTH, TRANSFER_ENCODING};
+        use log::warn;
+
+        let content_encoded: bool;
+        let mut is_encoded = {
+            content_encoded = headers
+                .get_all(CONTENT_ENCODING)
+                .iter()
+                .any(|enc| enc == encoding_str);
+            content_encoded
+                || headers
+                    .get_all(TRANSFER_ENCODING)
+                    .iter()
+                    .any(|enc| enc == encoding_str)
+        };
+        if is_encoded {
+            if let Some(content_length) = headers.get(CONTENT_LENGTH) {
+                if content_length == "0" {
+                    warn!("{} response with content-length of 0", encoding_str);
+                    is_encoded = false;
+                }
             }
         }
+        if content_encoded {
+            headers.remove(CONTENT_ENCODING);
+            headers.remove(CONTENT_LENGTH);
+        }
+        is_encoded
+    }
 
-        /// A plain text decoder.
-        ///
-        /// This decoder will emit the underlying chunks as-is.
-        fn plain_text(body: Body) -> Decoder {
-            Decoder {
-                inner: Inner::PlainText(body.into_stream()),
+    /// Constructs a Decoder from a hyper request.
+    ///
+    /// A decoder is just a wrapper around the hyper request that knows
+    /// how to decode the content body of the request.
+    ///
+    /// Uses the correct variant by inspecting the Content-Encoding header.
+    pub(super) fn detect(_headers: &mut HeaderMap, body: Body, _accepts: Accepts) -> Decoder {
+        #[cfg(feature = "gzip")]
+        {
+            if _accepts.gzip && Decoder::detect_encoding(_headers, "gzip") {
+                return Decoder::gzip(body);
             }
         }
 
-        /// A gzip decoder.
-        ///
-        /// This decoder will buffer and decompress chunks that are gzipped.
-        fn gzip(body: Body) -> Decoder {
-            use futures_util::StreamExt;
+        #[cfg(feature = "brotli")]
+        {
+            if _accepts.brotli && Decoder::detect_encoding(_headers, "br") {
+                return Decoder::brotli(body);
+            }
+        }
 
-            Decoder {
-                inner: Inner::Pending(Pending(IoStream(body.into_stream()).peekable())),
+        #[cfg(feature = "deflate")]
+        {
+            if _accepts.deflate && Decoder::detect_encoding(_headers, "deflate") {
+                return Decoder::deflate(body);
             }
         }
 
-        /// Constructs a Decoder from a hyper request.
-        ///
-        /// A decoder is just a wrapper around the hyper request that knows
-        /// how to decode the content body of the request.
-        ///
-        /// Uses the correct variant by inspecting the Content-Encoding header.
-        pub(crate) fn detect(headers: &mut HeaderMap, body: Body, check_gzip: bool) -> Decoder {
-            if !check_gzip {
-                return Decoder::plain_text(body);
+        #[cfg(feature = "zstd")]
+        {
+            if _accepts.zstd && Decoder::detect_encoding(_headers, "zstd") {
+                return Decoder::zstd(body);
             }
-            let content_encoding_gzip: bool;
-            let mut is_gzip = {
-                content_encoding_gzip = headers
-                    .get_all(CONTENT_ENCODING)
-                    .iter()
-                    .any(|enc| enc == "gzip");
-                content_encoding_gzip
-                    || headers
-                        .get_all(TRANSFER_ENCODING)
-                        .iter()
-                        .any(|enc| enc == "gzip")
-            };
-            if is_gzip {
-                if let Some(content_length) = headers.get(CONTENT_LENGTH) {
-                    if content_length == "0" {
-                        warn!("gzip response with content-length of 0");
-                        is_gzip = false;
-                    }
+        }
+
+        Decoder::plain_text(body)
+    }
+}
+
+impl Stream for Decoder {
+    type Item = Result<Bytes, error::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        // Do a read or poll for a pending decoder value.
+        match self.inner {
+            #[cfg(any(
+                feature = "brotli",
+                feature = "gzip",
+                feature = "deflate",
+                feature = "zstd"
+            ))]
+            Inner::Pending(ref mut future) => match Pin::new(future).poll(cx) {
+                Poll::Ready(Ok(inner)) => {
+                    self.inner = inner;
+                    self.poll_next(cx)
                 }
+                Poll::Ready(Err(e)) => Poll::Ready(Some(Err(crate::error::decode_io(e)))),
+                Poll::Pending => Poll::Pending,
+            },
+            Inner::PlainText(ref mut body) => Pin::new(body).poll_next(cx),
+            #[cfg(feature = "gzip")]
+            Inner::Gzip(ref mut decoder) => poll_decoded(Pin::new(decoder), cx),
+            #[cfg(feature = "brotli")]
+            Inner::Brotli(ref mut decoder) => poll_decoded(Pin::new(decoder), cx),
+            #[cfg(feature = "deflate")]
+            Inner::Deflate(ref mut decoder) => poll_decoded(Pin::new(decoder), cx),
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(ref mut decoder) => poll_decoded(Pin::new(decoder), cx),
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+fn poll_decoded<S>(decoder: Pin<&mut S>, cx: &mut Context) -> Poll<Option<crate::Result<Bytes>>>
+where
+    S: Stream<Item = std::io::Result<Bytes>>,
+{
+    match futures_core::ready!(decoder.poll_next(cx)) {
+        Some(Ok(bytes)) => Poll::Ready(Some(Ok(bytes))),
+        Some(Err(err)) => Poll::Ready(Some(Err(crate::error::decode_io(err)))),
+        None => Poll::Ready(None),
+    }
+}
+
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+impl Future for Pending {
+    type Output = Result<Inner, std::io::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use futures_util::StreamExt;
+
+        match futures_core::ready!(Pin::new(&mut self.0).poll_peek(cx)) {
+            Some(Ok(_)) => {
+                // fallthrough
             }
-            if content_encoding_gzip {
-                headers.remove(CONTENT_ENCODING);
-                headers.remove(CONTENT_LENGTH);
-            }
-            if is_gzip {
-                Decoder::gzip(body)
-            } else {
-                Decoder::plain_text(body)
+            Some(Err(_e)) => {
+                // error was just a ref, so we need to really poll to move it
+                return Poll::Ready(Err(futures_core::ready!(
+                    Pin::new(&mut self.0).poll_next(cx)
+                )
+                .expect("just peeked Some")
+                .unwrap_err()));
             }
+            None => return Poll::Ready(Ok(Inner::PlainText(Body::empty().into_stream()))),
+        };
+
+        let body = std::mem::replace(
+            &mut self.0,
+            IoStream(Body::empty().into_stream()).peekable(),
+        );
+
+        match self.1 {
+            #[cfg(feature = "gzip")]
+            DecoderType::Gzip => Poll::Ready(Ok(Inner::Gzip(GzipDecoder::new(body)))),
+            #[cfg(feature = "brotli")]
+            DecoderType::Brotli => Poll::Ready(Ok(Inner::Brotli(BrotliDecoder::new(body)))),
+            #[cfg(feature = "deflate")]
+            DecoderType::Deflate => Poll::Ready(Ok(Inner::Deflate(ZlibDecoder::new(body)))),
+            #[cfg(feature = "zstd")]
+            DecoderType::Zstd => Poll::Ready(Ok(Inner::Zstd(ZstdDecoder::new(body)))),
         }
     }
+}
 
-    impl Stream for Decoder {
-        type Item = Result<Bytes, error::Error>;
-
-        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-            // Do a read or poll for a pending decoder value.
-            let new_value = match self.inner {
-                Inner::Pending(ref mut future) => match Pin::new(future).poll(cx) {
-                    Poll::Ready(Ok(inner)) => inner,
-                    Poll::Ready(Err(e)) => {
-                        return Poll::Ready(Some(Err(crate::error::decode_io(e))))
-                    }
-                    Poll::Pending => return Poll::Pending,
-                },
-                Inner::PlainText(ref mut body) => return Pin::new(body).poll_next(cx),
-                Inner::Gzip(ref mut decoder) => {
-                    return match futures_core::ready!(Pin::new(decoder).poll_next(cx)) {
-                        Some(Ok(bytes)) => Poll::Ready(Some(Ok(bytes))),
-                        Some(Err(err)) => Poll::Ready(Some(Err(crate::error::decode_io(err)))),
-                        None => Poll::Ready(None),
-                    }
-                }
-            };
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+impl Stream for IoStream {
+    type Item = Result<Bytes, std::io::Error>;
 
-            self.inner = new_value;
-            self.poll_next(cx)
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        match futures_core::ready!(Pin::new(&mut self.0).poll_next(cx)) {
+            Some(Ok(chunk)) => Poll::Ready(Some(Ok(chunk))),
+            Some(Err(err)) => Poll::Ready(Some(Err(err.into_io()))),
+            None => Poll::Ready(None),
         }
     }
+}
 
-    impl Future for Pending {
-        type Output = Result<Inner, std::io::Error>;
+// ===== impl Accepts =====
 
-        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-            use futures_util::StreamExt;
+impl Accepts {
+    pub(super) fn none() -> Self {
+        Accepts {
+            #[cfg(feature = "gzip")]
+            gzip: false,
+            #[cfg(feature = "brotli")]
+            brotli: false,
+            #[cfg(feature = "deflate")]
+            deflate: false,
+            #[cfg(feature = "zstd")]
+            zstd: false,
+        }
+    }
 
-            match futures_core::ready!(Pin::new(&mut self.0).poll_peek(cx)) {
-                Some(Ok(_)) => {
-                    // fallthrough
-                }
-                Some(Err(_e)) => {
-                    // error was just a ref, so we need to really poll to move it
-                    return Poll::Ready(Err(futures_core::ready!(
-                        Pin::new(&mut self.0).poll_next(cx)
-                    )
-                    .expect("just peeked Some")
-                    .unwrap_err()));
-                }
-                None => return Poll::Ready(Ok(Inner::PlainText(Body::empty().into_stream()))),
-            };
+    /// The value of the `Accept-Encoding` header to send, if any encoding is
+    /// enabled.
+    pub(super) fn as_str(&self) -> Option<&'static str> {
+        match (
+            self.is_gzip(),
+            self.is_brotli(),
+            self.is_deflate(),
+            self.is_zstd(),
+        ) {
+            (true, true, true, true) => Some("gzip, br, deflate, zstd"),
+            (true, true, true, false) => Some("gzip, br, deflate"),
+            (true, true, false, true) => Some("gzip, br, zstd"),
+            (true, true, false, false) => Some("gzip, br"),
+            (true, false, true, true) => Some("gzip, deflate, zstd"),
+            (true, false, true, false) => Some("gzip, deflate"),
+            (true, false, false, true) => Some("gzip, zstd"),
+            (true, false, false, false) => Some("gzip"),
+            (false, true, true, true) => Some("br, deflate, zstd"),
+            (false, true, true, false) => Some("br, deflate"),
+            (false, true, false, true) => Some("br, zstd"),
+            (false, true, false, false) => Some("br"),
+            (false, false, true, true) => Some("deflate, zstd"),
+            (false, false, true, false) => Some("deflate"),
+            (false, false, false, true) => Some("zstd"),
+            (false, false, false, false) => None,
+        }
+    }
 
-            let body = mem::replace(
-                &mut self.0,
-                IoStream(Body::empty().into_stream()).peekable(),
-            );
-            Poll::Ready(Ok(Inner::Gzip(GzipDecoder::new(body))))
+    fn is_gzip(&self) -> bool {
+        #[cfg(feature = "gzip")]
+        {
+            self.gzip
+        }
+
+        #[cfg(not(feature = "gzip"))]
+        {
+            false
         }
     }
 
-    impl Stream for IoStream {
-        type Item = Result<Bytes, std::io::Error>;
+    fn is_brotli(&self) -> bool {
+        #[cfg(feature = "brotli")]
+        {
+            self.brotli
+        }
+
+        #[cfg(not(feature = "brotli"))]
+        {
+            false
+        }
+    }
 
-        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-            match futures_core::ready!(Pin::new(&mut self.0).poll_next(cx)) {
-                Some(Ok(chunk)) => Poll::Ready(Some(Ok(chunk))),
-                Some(Err(err)) => Poll::Ready(Some(Err(err.into_io()))),
-                None => Poll::Ready(None),
-            }
+    fn is_deflate(&self) -> bool {
+        #[cfg(feature = "deflate")]
+        {
+            self.deflate
+        }
+
+        #[cfg(not(feature = "deflate"))]
+        {
+            false
+        }
+    }
+
+    fn is_zstd(&self) -> bool {
+        #[cfg(feature = "zstd")]
+        {
+            self.zstd
+        }
+
+        #[cfg(not(feature = "zstd"))]
+        {
+            false
+        }
+    }
+}
+
+impl Default for Accepts {
+    fn default() -> Accepts {
+        Accepts {
+            #[cfg(feature = "gzip")]
+            gzip: true,
+            #[cfg(feature = "brotli")]
+            brotli: true,
+            #[cfg(feature = "deflate")]
+            deflate: true,
+            #[cfg(feature = "zstd")]
+            zstd: true,
         }
     }
 }
@@ -0,0 +1,106 @@
+use std::io;
+use std::pin::Pin;
+
+#[cfg(feature = "brotli")]
+use async_compression::stream::BrotliEncoder;
+#[cfg(feature = "gzip")]
+use async_compression::stream::GzipEncoder;
+#[cfg(feature = "deflate")]
+use async_compression::stream::ZlibEncoder;
+#[cfg(feature = "zstd")]
+use async_compression::stream::ZstdEncoder;
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::{future, FutureExt, TryStreamExt};
+
+use super::Body;
+
+type EncodedStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send + Sync>>;
+
+/// A content coding used to compress a request body.
+///
+/// See `RequestBuilder::compress`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// The `gzip` content coding.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// The `br` (Brotli) content coding.
+    #[cfg(feature = "brotli")]
+    Brotli,
+    /// The `deflate` content coding, a zlib stream.
+    #[cfg(feature = "deflate")]
+    Deflate,
+    /// The `zstd` (Zstandard) content coding.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Encoding {
+    /// The value of the `Content-Encoding` header for this coding.
+    pub(crate) fn as_str(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "gzip")]
+            Encoding::Gzip => "gzip",
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli => "br",
+            #[cfg(feature = "deflate")]
+            Encoding::Deflate => "deflate",
+            #[cfg(feature = "zstd")]
+            Encoding::Zstd => "zstd",
+        }
+    }
+
+    fn encode<S>(self, stream: S) -> EncodedStream
+    where
+        S: Stream<Item = io::Result<Bytes>> + Send + Sync + 'static,
+    {
+        match self {
+            #[cfg(feature = "gzip")]
+            Encoding::Gzip => Box::pin(GzipEncoder::new(
+                stream,
+                async_compression::flate2::Compression::default(),
+            )),
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli => Box::pin(BrotliEncoder::from_params(
+                stream,
+                &async_compression::brotli2::CompressParams::new(),
+            )),
+            #[cfg(feature = "deflate")]
+            Encoding::Deflate => Box::pin(ZlibEncoder::new(
+                stream,
+                async_compression::flate2::Compression::default(),
+            )),
+            // Level 0 picks zstd's default level.
+            #[cfg(feature = "zstd")]
+            Encoding::Zstd => Box::pin(ZstdEncoder::new(stream, 0)),
+        }
+    }
+}
+
+/// Compresses a request body.
+///
+/// A reusable body is compressed all at once, so that it stays reusable and
+/// its length is known. A streaming body is compressed as it is sent.
+pub(super) fn compress(body: Body, encoding: Encoding) -> crate::Result<Body> {
+    let (reusable, body) = body.try_reuse();
+    match reusable {
+        Some(bytes) => {
+            let chunk = futures_util::stream::once(future::ready(Ok(bytes)));
+            let compressed = encoding
+                .encode(chunk)
+                .try_fold(Vec::new(), |mut buf, chunk| {
+                    buf.extend_from_slice(&chunk);
+                    future::ready(Ok(buf))
+                })
+                .now_or_never()
+                .expect("compressing a ready chunk never waits")
+                .map_err(crate::error::body)?;
+            Ok(Body::reusable(compressed.into()))
+        }
+        None => {
+            let stream = body.into_stream().map_err(crate::error::into_io);
+            Ok(Body::stream(encoding.encode(stream)))
+        }
+    }
+}
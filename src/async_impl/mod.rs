@@ -1,12 +1,26 @@
 pub use self::body::Body;
 pub use self::client::{Client, ClientBuilder};
 pub(crate) use self::decoder::Decoder;
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+pub use self::encoder::Encoding;
 pub use self::request::{Request, RequestBuilder};
 pub use self::response::{Response, ResponseBuilderExt};
 
 pub mod body;
 pub mod client;
 pub mod decoder;
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+mod encoder;
 pub mod multipart;
 pub(crate) mod request;
 mod response;
@@ -13,9 +13,24 @@ use serde_urlencoded;
 
 use super::body::Body;
 use super::client::{Client, Pending};
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+use super::encoder::{self, Encoding};
 use super::multipart;
 use super::response::Response;
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+use crate::header::CONTENT_ENCODING;
 use crate::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use crate::retry;
 use crate::{Method, Url};
 
 /// A request which can be executed with `Client::execute()`.
@@ -25,6 +40,14 @@ pub struct Request {
     headers: HeaderMap,
     body: Option<Body>,
     timeout: Option<Duration>,
+    retry: Option<retry::Policy>,
+    #[cfg(any(
+        feature = "brotli",
+        feature = "gzip",
+        feature = "deflate",
+        feature = "zstd"
+    ))]
+    compress: Option<Encoding>,
 }
 
 /// A builder to construct the properties of a `Request`.
@@ -42,7 +65,15 @@ impl Request {
             url,
             headers: HeaderMap::new(),
             body: None,
-            timeout: None
+            timeout: None,
+            retry: None,
+            #[cfg(any(
+                feature = "brotli",
+                feature = "gzip",
+                feature = "deflate",
+                feature = "zstd"
+            ))]
+            compress: None,
         }
     }
 
@@ -106,6 +137,42 @@ impl Request {
         &mut self.timeout
     }
 
+    /// Get the retry policy, if one overrides the client's.
+    #[inline]
+    pub fn retry(&self) -> Option<&retry::Policy> {
+        self.retry.as_ref()
+    }
+
+    /// Get a mutable reference to the retry policy.
+    #[inline]
+    pub fn retry_mut(&mut self) -> &mut Option<retry::Policy> {
+        &mut self.retry
+    }
+
+    /// Get the encoding the body is compressed with when sent.
+    #[cfg(any(
+        feature = "brotli",
+        feature = "gzip",
+        feature = "deflate",
+        feature = "zstd"
+    ))]
+    #[inline]
+    pub fn compress(&self) -> Option<&Encoding> {
+        self.compress.as_ref()
+    }
+
+    /// Get a mutable reference to the body compression encoding.
+    #[cfg(any(
+        feature = "brotli",
+        feature = "gzip",
+        feature = "deflate",
+        feature = "zstd"
+    ))]
+    #[inline]
+    pub fn compress_mut(&mut self) -> &mut Option<Encoding> {
+        &mut self.compress
+    }
+
     /// Attempt to clone the request.
     ///
     /// `None` is returned if the request can not be cloned, i.e. if the body is a stream.
@@ -116,13 +183,67 @@ impl Request {
         };
         let mut req = Request::new(self.method().clone(), self.url().clone());
         *req.timeout_mut() = self.timeout().cloned();
+        *req.retry_mut() = self.retry().cloned();
+        #[cfg(any(
+            feature = "brotli",
+            feature = "gzip",
+            feature = "deflate",
+            feature = "zstd"
+        ))]
+        {
+            *req.compress_mut() = self.compress().cloned();
+        }
         *req.headers_mut() = self.headers().clone();
         req.body = body;
         Some(req)
     }
 
-    pub(super) fn pieces(self) -> (Method, Url, HeaderMap, Option<Body>, Option<Duration>) {
-        (self.method, self.url, self.headers, self.body, self.timeout)
+    /// Compresses the body, if an encoding was set, and updates the
+    /// `Content-Encoding` and `Content-Length` headers to match.
+    #[cfg(any(
+        feature = "brotli",
+        feature = "gzip",
+        feature = "deflate",
+        feature = "zstd"
+    ))]
+    pub(super) fn compress_body(&mut self) -> crate::Result<()> {
+        let encoding = match self.compress.take() {
+            Some(encoding) => encoding,
+            None => return Ok(()),
+        };
+        if let Some(body) = self.body.take() {
+            let body = encoder::compress(body, encoding)?;
+            self.headers.insert(
+                CONTENT_ENCODING,
+                HeaderValue::from_static(encoding.as_str()),
+            );
+            match body.content_length() {
+                Some(len) => self.headers.insert(CONTENT_LENGTH, len.into()),
+                None => self.headers.remove(CONTENT_LENGTH),
+            };
+            self.body = Some(body);
+        }
+        Ok(())
+    }
+
+    pub(super) fn pieces(
+        self,
+    ) -> (
+        Method,
+        Url,
+        HeaderMap,
+        Option<Body>,
+        Option<Duration>,
+        Option<retry::Policy>,
+    ) {
+        (
+            self.method,
+            self.url,
+            self.headers,
+            self.body,
+            self.timeout,
+            self.retry,
+        )
     }
 }
 
@@ -227,6 +348,43 @@ impl RequestBuilder {
         self
     }
 
+    /// Set a `retry::Policy` for this request.
+    ///
+    /// It overrides the policy configured using `ClientBuilder::retry()`.
+    /// Use `retry::Policy::none()` to disable retries for this request.
+    pub fn retry(mut self, policy: retry::Policy) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.retry_mut() = Some(policy);
+        }
+        self
+    }
+
+    /// Compress the request body with the given `Encoding` when it is sent.
+    ///
+    /// The body, whether reusable or a stream, is compressed and the
+    /// `Content-Encoding` header is set. A reusable body is compressed up
+    /// front, and the `Content-Length` header is set to the compressed
+    /// length; a stream is compressed as it is sent, without a
+    /// `Content-Length`.
+    ///
+    /// # Optional
+    ///
+    /// This requires at least one of the optional `gzip`, `brotli`,
+    /// `deflate` or `zstd` features to be enabled, and only offers the
+    /// encodings of the enabled features.
+    #[cfg(any(
+        feature = "brotli",
+        feature = "gzip",
+        feature = "deflate",
+        feature = "zstd"
+    ))]
+    pub fn compress(mut self, encoding: Encoding) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.compress_mut() = Some(encoding);
+        }
+        self
+    }
+
     /// Sends a multipart/form-data body.
     ///
     /// ```
@@ -1,6 +1,7 @@
 use std::borrow::Cow;
 use std::fmt;
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use bytes::{Bytes, BytesMut};
 use encoding_rs::{Encoding, UTF_8};
@@ -19,7 +20,9 @@ use tokio::time::Delay;
 use url::Url;
 
 use super::body::Body;
+use super::decoder::Accepts;
 use super::Decoder;
+use crate::timings::Timings;
 #[cfg(feature = "cookies")]
 use crate::cookie;
 
@@ -33,14 +36,18 @@ pub struct Response {
     body: Decoder,
     version: Version,
     extensions: http::Extensions,
+    // Boxed like `url`, since it's not accessed internally.
+    timings: Box<Timings>,
 }
 
 impl Response {
     pub(super) fn new(
         res: hyper::Response<hyper::Body>,
         url: Url,
-        gzip: bool,
+        accepts: Accepts,
         timeout: Option<Delay>,
+        read_timeout: Option<Duration>,
+        timings: Timings,
     ) -> Response {
         let (parts, body) = res.into_parts();
         let status = parts.status;
@@ -48,7 +55,7 @@ impl Response {
         let extensions = parts.extensions;
 
         let mut headers = parts.headers;
-        let decoder = Decoder::detect(&mut headers, Body::response(body, timeout), gzip);
+        let decoder = Decoder::detect(&mut headers, Body::response(body, timeout, read_timeout), accepts);
 
         debug!("Response: '{}' for {}", status, url);
         Response {
@@ -58,6 +65,7 @@ impl Response {
             body: decoder,
             version,
             extensions,
+            timings: Box::new(timings),
         }
     }
 
@@ -124,6 +132,26 @@ impl Response {
             .map(|info| info.remote_addr())
     }
 
+    /// Get how long the phases of the request took.
+    ///
+    /// The timings of the redirects followed before this response are in
+    /// `Timings::redirects`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), reqwest::Error> {
+    /// let res = reqwest::get("http://httpbin.org/redirect/1").await?;
+    /// let timings = res.timings();
+    /// println!("waited {:?} for the server", timings.ttfb());
+    /// println!("followed {} redirects", timings.redirects().len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
     // body methods
 
     /// Get the full response text.
@@ -406,7 +434,7 @@ impl<T: Into<Body>> From<http::Response<T>> for Response {
     fn from(r: http::Response<T>) -> Response {
         let (mut parts, body) = r.into_parts();
         let body = body.into();
-        let body = Decoder::detect(&mut parts.headers, body, false);
+        let body = Decoder::detect(&mut parts.headers, body, Accepts::none());
         let url = parts
             .extensions
             .remove::<ResponseUrl>()
@@ -419,6 +447,7 @@ impl<T: Into<Body>> From<http::Response<T>> for Response {
             body,
             version: parts.version,
             extensions: parts.extensions,
+            timings: Box::new(Timings::default()),
         }
     }
 }
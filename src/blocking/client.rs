@@ -1,21 +1,31 @@
+#[cfg(feature = "__tls")]
+use std::any::Any;
 use std::convert::TryInto;
+use std::error::Error as StdError;
 use std::fmt;
 use std::future::Future;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
+#[cfg(unix)]
+use std::path::Path;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
 use http::header::HeaderValue;
+use http::Uri;
+use hyper::service::Service;
 use log::{error, trace};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::{mpsc, oneshot};
 
 use super::request::{Request, RequestBuilder};
 use super::response::Response;
 use super::wait;
-use crate::{async_impl, header, IntoUrl, Method, Proxy, redirect};
+use crate::dns::{AddressFamily, Resolve};
+use crate::middleware::Middleware;
+use crate::{async_impl, header, IntoUrl, Method, Proxy, redirect, retry, Stats};
 #[cfg(feature = "__tls")]
-use crate::{Certificate, Identity};
+use crate::{Certificate, Identity, TlsVersion};
 
 /// A `Client` to make Requests with.
 ///
@@ -178,7 +188,8 @@ impl ClientBuilder {
     /// If auto gzip decompresson is turned on:
     ///
     /// - When sending a request and if the request's headers do not already contain
-    ///   an `Accept-Encoding` **and** `Range` values, the `Accept-Encoding` header is set to `gzip`.
+    ///   an `Accept-Encoding` **and** `Range` values, `gzip` is added to the
+    ///   `Accept-Encoding` header, along with any other enabled encoding.
     ///   The request body is **not** automatically compressed.
     /// - When receiving a response, if it's headers contain a `Content-Encoding` value that
     ///   equals to `gzip`, both values `Content-Encoding` and `Content-Length` are removed from the
@@ -203,6 +214,99 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.no_gzip())
     }
 
+    /// Enable auto brotli decompression by checking the `Content-Encoding` response header.
+    ///
+    /// If auto brotli decompression is turned on:
+    ///
+    /// - When sending a request and if the request's headers do not already contain
+    ///   an `Accept-Encoding` **and** `Range` values, `br` is added to the
+    ///   `Accept-Encoding` header, along with any other enabled encoding.
+    ///   The request body is **not** automatically compressed.
+    /// - When receiving a response, if it's headers contain a `Content-Encoding` value that
+    ///   equals to `br`, both values `Content-Encoding` and `Content-Length` are removed from the
+    ///   headers' set. The response body is automatically decompressed.
+    ///
+    /// If the `brotli` feature is turned on, the default option is enabled.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `brotli` feature to be enabled
+    #[cfg(feature = "brotli")]
+    pub fn brotli(self, enable: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.brotli(enable))
+    }
+
+    /// Disable auto response body brotli decompression.
+    ///
+    /// This method exists even if the optional `brotli` feature is not enabled.
+    /// This can be used to ensure a `Client` doesn't use brotli decompression
+    /// even if another dependency were to enable the optional `brotli` feature.
+    pub fn no_brotli(self) -> ClientBuilder {
+        self.with_inner(|inner| inner.no_brotli())
+    }
+
+    /// Enable auto deflate decompression by checking the `Content-Encoding` response header.
+    ///
+    /// If auto deflate decompression is turned on:
+    ///
+    /// - When sending a request and if the request's headers do not already contain
+    ///   an `Accept-Encoding` **and** `Range` values, `deflate` is added to the
+    ///   `Accept-Encoding` header, along with any other enabled encoding.
+    ///   The request body is **not** automatically compressed.
+    /// - When receiving a response, if it's headers contain a `Content-Encoding` value that
+    ///   equals to `deflate`, both values `Content-Encoding` and `Content-Length` are removed from the
+    ///   headers' set. The response body is automatically decompressed.
+    ///
+    /// If the `deflate` feature is turned on, the default option is enabled.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `deflate` feature to be enabled
+    #[cfg(feature = "deflate")]
+    pub fn deflate(self, enable: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.deflate(enable))
+    }
+
+    /// Disable auto response body deflate decompression.
+    ///
+    /// This method exists even if the optional `deflate` feature is not enabled.
+    /// This can be used to ensure a `Client` doesn't use deflate decompression
+    /// even if another dependency were to enable the optional `deflate` feature.
+    pub fn no_deflate(self) -> ClientBuilder {
+        self.with_inner(|inner| inner.no_deflate())
+    }
+
+    /// Enable auto zstd decompression by checking the `Content-Encoding` response header.
+    ///
+    /// If auto zstd decompression is turned on:
+    ///
+    /// - When sending a request and if the request's headers do not already contain
+    ///   an `Accept-Encoding` **and** `Range` values, `zstd` is added to the
+    ///   `Accept-Encoding` header, along with any other enabled encoding.
+    ///   The request body is **not** automatically compressed.
+    /// - When receiving a response, if it's headers contain a `Content-Encoding` value that
+    ///   equals to `zstd`, both values `Content-Encoding` and `Content-Length` are removed from the
+    ///   headers' set. The response body is automatically decompressed.
+    ///
+    /// If the `zstd` feature is turned on, the default option is enabled.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `zstd` feature to be enabled
+    #[cfg(feature = "zstd")]
+    pub fn zstd(self, enable: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.zstd(enable))
+    }
+
+    /// Disable auto response body zstd decompression.
+    ///
+    /// This method exists even if the optional `zstd` feature is not enabled.
+    /// This can be used to ensure a `Client` doesn't use zstd decompression
+    /// even if another dependency were to enable the optional `zstd` feature.
+    pub fn no_zstd(self) -> ClientBuilder {
+        self.with_inner(|inner| inner.no_zstd())
+    }
+
     // Redirect options
 
     /// Set a `redirect::Policy` for this client.
@@ -219,6 +323,34 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.referer(enable))
     }
 
+    // Retry options
+
+    /// Set a `retry::Policy` for this client.
+    ///
+    /// It can be overridden for a single request with
+    /// `RequestBuilder::retry()`.
+    ///
+    /// Default will not retry any request.
+    pub fn retry(self, policy: retry::Policy) -> ClientBuilder {
+        self.with_inner(move |inner| inner.retry(policy))
+    }
+
+    // Middleware options
+
+    /// Add a `Middleware` to this client.
+    ///
+    /// It is called with every request before it is sent, including the
+    /// requests following redirects, and with every response before it is
+    /// returned. Middleware are called in the order they are added for
+    /// requests, and in the reverse order for responses.
+    ///
+    /// The blocking client sends requests with the async client, so a
+    /// middleware works with the async `reqwest::Request` and
+    /// `reqwest::Response` types.
+    pub fn middleware(self, middleware: Arc<dyn Middleware>) -> ClientBuilder {
+        self.with_inner(move |inner| inner.middleware(middleware))
+    }
+
     // Proxy options
 
     /// Add a `Proxy` to the list of proxies the `Client` will use.
@@ -273,11 +405,49 @@ impl ClientBuilder {
         }
     }
 
+    /// Set a timeout for reading the body of a response, which fires when no
+    /// data arrives for `timeout`.
+    ///
+    /// Unlike `timeout`, this doesn't limit slow but steady downloads. It
+    /// starts when the response headers are received, and restarts each
+    /// time data arrives.
+    ///
+    /// Default is `None`.
+    pub fn read_timeout<T>(self, timeout: T) -> ClientBuilder
+    where
+        T: Into<Option<Duration>>,
+    {
+        let timeout = timeout.into();
+        if let Some(dur) = timeout {
+            self.with_inner(|inner| inner.read_timeout(dur))
+        } else {
+            self
+        }
+    }
+
     // HTTP options
 
+    /// Set an optional timeout for idle sockets being kept-alive.
+    ///
+    /// Pass `None` to disable timeout.
+    ///
+    /// Default is 90 seconds.
+    pub fn pool_idle_timeout<D>(self, val: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.with_inner(|inner| inner.pool_idle_timeout(val))
+    }
+
     /// Sets the maximum idle connection per host allowed in the pool.
+    pub fn pool_max_idle_per_host(self, max: usize) -> ClientBuilder {
+        self.with_inner(move |inner| inner.pool_max_idle_per_host(max))
+    }
+
+    #[doc(hidden)]
+    #[deprecated(note = "renamed to `pool_max_idle_per_host`")]
     pub fn max_idle_per_host(self, max: usize) -> ClientBuilder {
-        self.with_inner(move |inner| inner.max_idle_per_host(max))
+        self.pool_max_idle_per_host(max)
     }
 
     /// Enable case sensitive headers.
@@ -329,6 +499,160 @@ impl ClientBuilder {
         self.with_inner(move |inner| inner.local_address(addr))
     }
 
+    /// Set that all sockets send TCP keepalive probes after being idle for
+    /// `interval`.
+    ///
+    /// Default is `None`.
+    pub fn tcp_keepalive<D>(self, interval: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.with_inner(move |inner| inner.tcp_keepalive(interval))
+    }
+
+    /// Set the size of the send buffer of all sockets (`SO_SNDBUF`).
+    ///
+    /// Default is the operating system's default.
+    pub fn tcp_send_buffer_size(self, size: usize) -> ClientBuilder {
+        self.with_inner(move |inner| inner.tcp_send_buffer_size(size))
+    }
+
+    /// Set the size of the receive buffer of all sockets (`SO_RCVBUF`).
+    ///
+    /// Default is the operating system's default.
+    pub fn tcp_recv_buffer_size(self, size: usize) -> ClientBuilder {
+        self.with_inner(move |inner| inner.tcp_recv_buffer_size(size))
+    }
+
+    /// Bind all sockets to a network interface, such as `"eth0"`
+    /// (`SO_BINDTODEVICE`).
+    ///
+    /// This option is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn interface(self, interface: &str) -> ClientBuilder {
+        self.with_inner(move |inner| inner.interface(interface))
+    }
+
+    /// Set which IP address families to connect to.
+    ///
+    /// Resolved addresses can be ordered to try one family first, or
+    /// filtered to only use one family. `Response::remote_addr` tells which
+    /// address was connected to.
+    ///
+    /// Default is `AddressFamily::Any`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use reqwest::dns::AddressFamily;
+    /// let client = reqwest::blocking::Client::builder()
+    ///     .address_family(AddressFamily::PreferIpv4)
+    ///     .build().unwrap();
+    /// ```
+    pub fn address_family(self, family: AddressFamily) -> ClientBuilder {
+        self.with_inner(move |inner| inner.address_family(family))
+    }
+
+    /// Set how long to wait for a connection to the preferred address family
+    /// before also trying the other one ("Happy Eyeballs").
+    ///
+    /// Pass `None` to try every address in turn instead.
+    ///
+    /// Default is 300 milliseconds.
+    pub fn happy_eyeballs_timeout<D>(self, timeout: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.with_inner(move |inner| inner.happy_eyeballs_timeout(timeout))
+    }
+
+    // Connector options
+
+    /// Establish connections with a custom connector, instead of TCP.
+    ///
+    /// The connector is called with the URI of each destination, or of the
+    /// proxy when one is used, and returns any transport, such as a Unix
+    /// domain socket or an in-memory stream. HTTPS and proxy tunnels are
+    /// still established on top of the connections it returns.
+    ///
+    /// The DNS resolver and the TCP options, such as `local_address` and
+    /// `tcp_nodelay`, only apply to TCP, and are not used. Connections through a SOCKS proxy
+    /// don't use the connector.
+    pub fn connector<C>(self, connector: C) -> ClientBuilder
+    where
+        C: Service<Uri> + Clone + Send + Sync + 'static,
+        C::Response: AsyncRead + AsyncWrite + Send + Sync + Unpin + 'static,
+        C::Future: Send + 'static,
+        C::Error: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        self.with_inner(move |inner| inner.connector(connector))
+    }
+
+    /// Send every request over the Unix domain socket at `path`, instead of
+    /// TCP.
+    ///
+    /// The URL of a request still sets its `Host` header and path, so the
+    /// host can be any name the server accepts, such as `localhost`.
+    /// Redirects are followed over the same socket.
+    ///
+    /// This replaces any `connector`, and disables the system proxies.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), reqwest::Error> {
+    /// let client = reqwest::blocking::Client::builder()
+    ///     .unix_socket("/var/run/docker.sock")
+    ///     .build()?;
+    ///
+    /// let containers = client.get("http://localhost/containers/json").send()?.text()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn unix_socket<P: AsRef<Path>>(self, path: P) -> ClientBuilder {
+        self.with_inner(move |inner| inner.unix_socket(path))
+    }
+
+    // DNS options
+
+    /// Override the DNS resolver used by this `Client`.
+    ///
+    /// The resolver is used to look up the addresses of every destination
+    /// and proxy the `Client` connects to.
+    ///
+    /// Default is the system's `getaddrinfo`, run on a blocking threadpool.
+    pub fn dns_resolver(self, resolver: Arc<dyn Resolve>) -> ClientBuilder {
+        self.with_inner(move |inner| inner.dns_resolver(resolver))
+    }
+
+    /// Override DNS resolution for specific domains to a particular IP address.
+    ///
+    /// The URL is left untouched, so the `Host` header, TLS server name and
+    /// certificate validation all still use `domain`.
+    ///
+    /// # Warning
+    ///
+    /// Since the DNS protocol has no notion of ports, if you wish to send
+    /// traffic to a particular port you must include this port in the URL
+    /// itself, any port in the overridden addr will be ignored and traffic sent
+    /// to the conventional port for the given scheme (e.g. 80 for http).
+    pub fn resolve(self, domain: &str, addr: SocketAddr) -> ClientBuilder {
+        self.with_inner(|inner| inner.resolve(domain, addr))
+    }
+
+    /// Override DNS resolution for specific domains to particular IP addresses.
+    ///
+    /// # Warning
+    ///
+    /// Since the DNS protocol has no notion of ports, if you wish to send
+    /// traffic to a particular port you must include this port in the URL
+    /// itself, any port in the overridden addresses will be ignored and traffic sent
+    /// to the conventional port for the given scheme (e.g. 80 for http).
+    pub fn resolve_to_addrs(self, domain: &str, addrs: &[SocketAddr]) -> ClientBuilder {
+        self.with_inner(|inner| inner.resolve_to_addrs(domain, addrs))
+    }
+
     // TLS options
 
     /// Add a custom root certificate.
@@ -367,6 +691,77 @@ impl ClientBuilder {
         self.with_inner(move |inner| inner.add_root_certificate(cert))
     }
 
+    /// Controls the use of built-in root certificates during certificate
+    /// validation.
+    ///
+    /// When disabled, only the certificates added with
+    /// `add_root_certificate` are trusted. Defaults to `true`.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn tls_built_in_root_certs(self, tls_built_in_root_certs: bool) -> ClientBuilder {
+        self.with_inner(move |inner| inner.tls_built_in_root_certs(tls_built_in_root_certs))
+    }
+
+    /// Pin the public key of the certificate of `host`.
+    ///
+    /// `sha256_spki` is the SHA-256 hash of the DER `SubjectPublicKeyInfo`
+    /// of the certificate the server presents for itself. Once a host is
+    /// pinned, its TLS connections are only kept if the certificate matches
+    /// one of its pins.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn pin_certificate(self, host: &str, sha256_spki: [u8; 32]) -> ClientBuilder {
+        self.with_inner(move |inner| inner.pin_certificate(host, sha256_spki))
+    }
+
+    /// Set the minimum version of TLS to negotiate.
+    ///
+    /// `build` fails when the TLS backend can't honor it.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn min_tls_version(self, version: TlsVersion) -> ClientBuilder {
+        self.with_inner(move |inner| inner.min_tls_version(version))
+    }
+
+    /// Set the maximum version of TLS to negotiate.
+    ///
+    /// `build` fails when the TLS backend can't honor it.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn max_tls_version(self, version: TlsVersion) -> ClientBuilder {
+        self.with_inner(move |inner| inner.max_tls_version(version))
+    }
+
+    /// Restrict the cipher suites offered to the server, named as registered
+    /// with IANA.
+    ///
+    /// Only rustls supports selecting cipher suites; `build` fails otherwise.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn tls_cipher_suites(self, suites: &[&str]) -> ClientBuilder {
+        self.with_inner(move |inner| inner.tls_cipher_suites(suites))
+    }
+
     /// Sets the identity to be used for client certificate authentication.
     #[cfg(feature = "__tls")]
     pub fn identity(self, identity: Identity) -> ClientBuilder {
@@ -429,11 +824,26 @@ impl ClientBuilder {
     /// # Optional
     ///
     /// This requires the optional `rustls-tls` feature to be enabled.
-    #[cfg(feature = "rustls-tls")]
+    #[cfg(feature = "__rustls")]
     pub fn use_rustls_tls(self) -> ClientBuilder {
         self.with_inner(move |inner| inner.use_rustls_tls())
     }
 
+    /// Use a preconfigured TLS backend.
+    ///
+    /// `tls` is either a `native_tls::TlsConnector` or a
+    /// `rustls::ClientConfig`, which the `Client` uses as given. `build`
+    /// fails if `tls` is of any other type.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    pub fn use_preconfigured_tls(self, tls: impl Any) -> ClientBuilder {
+        self.with_inner(move |inner| inner.use_preconfigured_tls(tls))
+    }
+
     // private
 
     fn with_inner<F>(mut self, func: F) -> ClientBuilder
@@ -548,6 +958,26 @@ impl Client {
     pub fn execute(&self, request: Request) -> crate::Result<Response> {
         self.inner.execute_request(request)
     }
+
+    /// Closes the idle connections in the pool.
+    ///
+    /// Connections in use by a response are closed once that response is
+    /// done, instead of being returned to the pool. Later requests open new
+    /// connections.
+    ///
+    /// This is useful when the servers behind a host change, such as on a
+    /// configuration reload, so that requests stop reusing connections to
+    /// the old ones.
+    pub fn close_idle_connections(&self) {
+        self.inner.inner.client.close_idle_connections();
+    }
+
+    /// Returns a snapshot of the statistics of this `Client`.
+    ///
+    /// The statistics are shared with the clones of this `Client`.
+    pub fn stats(&self) -> Stats {
+        self.inner.inner.client.stats()
+    }
 }
 
 impl fmt::Debug for Client {
@@ -576,6 +1006,7 @@ type OneshotResponse = oneshot::Sender<crate::Result<async_impl::Response>>;
 type ThreadSender = mpsc::UnboundedSender<(async_impl::Request, OneshotResponse)>;
 
 struct InnerClientHandle {
+    client: async_impl::Client,
     tx: Option<ThreadSender>,
     thread: Option<thread::JoinHandle<()>>,
 }
@@ -600,7 +1031,7 @@ impl ClientHandle {
         let timeout = builder.timeout;
         let builder = builder.inner;
         let (tx, rx) = mpsc::unbounded_channel::<(async_impl::Request, OneshotResponse)>();
-        let (spawn_tx, spawn_rx) = oneshot::channel::<crate::Result<()>>();
+        let (spawn_tx, spawn_rx) = oneshot::channel::<crate::Result<async_impl::Client>>();
         let handle = thread::Builder::new()
             .name("reqwest-internal-sync-runtime".into())
             .spawn(move || {
@@ -625,7 +1056,7 @@ impl ClientHandle {
                         }
                         Ok(v) => v,
                     };
-                    if let Err(e) = spawn_tx.send(Ok(())) {
+                    if let Err(e) = spawn_tx.send(Ok(client.clone())) {
                         error!("Failed to communicate successful startup: {:?}", e);
                         return;
                     }
@@ -649,13 +1080,14 @@ impl ClientHandle {
             .map_err(crate::error::builder)?;
 
         // Wait for the runtime thread to start up...
-        match wait::timeout(spawn_rx, None) {
-            Ok(Ok(())) => (),
+        let client = match wait::timeout(spawn_rx, None) {
+            Ok(Ok(client)) => client,
             Ok(Err(err)) => return Err(err),
             Err(_canceled) => event_loop_panicked(),
-        }
+        };
 
         let inner_handle = Arc::new(InnerClientHandle {
+            client,
             tx: Some(tx),
             thread: Some(handle),
         });
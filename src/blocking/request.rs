@@ -11,7 +11,14 @@ use super::body::{self, Body};
 use super::multipart;
 use super::Client;
 use crate::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
-use crate::{async_impl, Method, Url};
+#[cfg(any(
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate",
+    feature = "zstd"
+))]
+use crate::Encoding;
+use crate::{async_impl, retry, Method, Url};
 
 /// A request which can be executed with `Client::execute()`.
 pub struct Request {
@@ -84,6 +91,42 @@ impl Request {
         &mut self.body
     }
 
+    /// Get the retry policy, if one overrides the client's.
+    #[inline]
+    pub fn retry(&self) -> Option<&retry::Policy> {
+        self.inner.retry()
+    }
+
+    /// Get a mutable reference to the retry policy.
+    #[inline]
+    pub fn retry_mut(&mut self) -> &mut Option<retry::Policy> {
+        self.inner.retry_mut()
+    }
+
+    /// Get the encoding the body is compressed with when sent.
+    #[cfg(any(
+        feature = "brotli",
+        feature = "gzip",
+        feature = "deflate",
+        feature = "zstd"
+    ))]
+    #[inline]
+    pub fn compress(&self) -> Option<&Encoding> {
+        self.inner.compress()
+    }
+
+    /// Get a mutable reference to the body compression encoding.
+    #[cfg(any(
+        feature = "brotli",
+        feature = "gzip",
+        feature = "deflate",
+        feature = "zstd"
+    ))]
+    #[inline]
+    pub fn compress_mut(&mut self) -> &mut Option<Encoding> {
+        self.inner.compress_mut()
+    }
+
     /// Attempts to clone the `Request`.
     ///
     /// None is returned if a body is which can not be cloned. This can be because the body is a
@@ -100,6 +143,16 @@ impl Request {
         };
         let mut req = Request::new(self.method().clone(), self.url().clone());
         *req.headers_mut() = self.headers().clone();
+        *req.retry_mut() = self.retry().cloned();
+        #[cfg(any(
+            feature = "brotli",
+            feature = "gzip",
+            feature = "deflate",
+            feature = "zstd"
+        ))]
+        {
+            *req.compress_mut() = self.compress().cloned();
+        }
         req.body = body;
         Some(req)
     }
@@ -300,6 +353,46 @@ impl RequestBuilder {
         self
     }
 
+    /// Set a `retry::Policy` for this request.
+    ///
+    /// It overrides the policy configured using `ClientBuilder::retry()`.
+    /// Use `retry::Policy::none()` to disable retries for this request.
+    ///
+    /// Only bodies created from bytes can be sent again; a request with a
+    /// body read from a `File` or any other `Read` is never retried.
+    pub fn retry(mut self, policy: retry::Policy) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.retry_mut() = Some(policy);
+        }
+        self
+    }
+
+    /// Compress the request body with the given `Encoding` when it is sent.
+    ///
+    /// The body is compressed and the `Content-Encoding` header is set. A
+    /// body created from bytes is compressed up front, and the
+    /// `Content-Length` header is set to the compressed length; a body read
+    /// from a `File` or any other `Read` is compressed as it is sent,
+    /// without a `Content-Length`.
+    ///
+    /// # Optional
+    ///
+    /// This requires at least one of the optional `gzip`, `brotli`,
+    /// `deflate` or `zstd` features to be enabled, and only offers the
+    /// encodings of the enabled features.
+    #[cfg(any(
+        feature = "brotli",
+        feature = "gzip",
+        feature = "deflate",
+        feature = "zstd"
+    ))]
+    pub fn compress(mut self, encoding: Encoding) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.compress_mut() = Some(encoding);
+        }
+        self
+    }
+
     /// Modify the query string of the URL.
     ///
     /// Modifies the URL of this request, adding the parameters provided.
@@ -14,7 +14,7 @@ use super::client::KeepCoreThreadAlive;
 use super::wait;
 #[cfg(feature = "cookies")]
 use crate::cookie;
-use crate::{async_impl, StatusCode, Url, Version};
+use crate::{async_impl, StatusCode, Timings, Url, Version};
 
 /// A Response to a submitted `Request`.
 pub struct Response {
@@ -170,6 +170,24 @@ impl Response {
         self.inner.remote_addr()
     }
 
+    /// Get how long the phases of the request took.
+    ///
+    /// The timings of the redirects followed before this response are in
+    /// `Timings::redirects`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn run() -> Result<(), Box<std::error::Error>> {
+    /// let resp = reqwest::blocking::get("http://httpbin.org/redirect/1")?;
+    /// println!("waited {:?} for the server", resp.timings().ttfb());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn timings(&self) -> &Timings {
+        self.inner.timings()
+    }
+
     /// Get the content-length of the response, if it is known.
     ///
     /// Reasons it may not be known:
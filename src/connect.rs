@@ -4,55 +4,76 @@ use http::Uri;
 use hyper::client::connect::{Connected, Connection};
 use tokio::io::{AsyncRead, AsyncWrite};
 #[cfg(feature = "native-tls-crate")]
-use native_tls_crate::{TlsConnector, TlsConnectorBuilder};
+use native_tls_crate::TlsConnector;
 #[cfg(feature = "__tls")]
-use http::header::HeaderValue;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+#[cfg(feature = "__tls")]
+use http::Method;
 use bytes::{Buf, BufMut};
 
+#[cfg(feature = "__tls")]
+use std::error::Error as StdError;
+#[cfg(feature = "__tls")]
+use std::fmt;
 use std::future::Future;
+#[cfg(target_os = "linux")]
+use std::ffi::CString;
 use std::io;
 use std::net::IpAddr;
+#[cfg(target_os = "linux")]
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::Path;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use std::mem::MaybeUninit;
 use pin_project_lite::pin_project;
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 
-//#[cfg(feature = "trust-dns")]
-//use crate::dns::TrustDnsResolver;
+use crate::dns::DynResolver;
 use crate::proxy::{Proxy, ProxyScheme};
+#[cfg(feature = "__tls")]
+use crate::proxy_auth::ProxyChallenge;
+use crate::proxy_auth::AuthHandler;
 use crate::error::BoxError;
-#[cfg(feature = "default-tls")]
-use self::native_tls_conn::NativeTlsConn;
-#[cfg(feature = "rustls-tls")]
+use crate::stats::{ConnInfo, Counters};
+use crate::timings::ConnectTimings;
+#[cfg(feature = "__tls")]
+use crate::tls::CertificatePins;
+#[cfg(feature = "__rustls")]
 use self::rustls_tls_conn::RustlsTlsConn;
 
-//#[cfg(feature = "trust-dns")]
-//type HttpConnector = hyper::client::HttpConnector<TrustDnsResolver>;
-//#[cfg(not(feature = "trust-dns"))]
-type HttpConnector = hyper::client::HttpConnector;
+type HttpConnector = hyper::client::HttpConnector<DynResolver>;
 
 #[derive(Clone)]
 pub(crate) struct Connector {
     inner: Inner,
     proxies: Arc<Vec<Proxy>>,
+    #[cfg(feature = "socks")]
+    resolver: DynResolver,
     timeout: Option<Duration>,
     #[cfg(feature = "__tls")]
     nodelay: bool,
     #[cfg(feature = "__tls")]
     user_agent: Option<HeaderValue>,
+    #[cfg(feature = "__tls")]
+    pins: Arc<CertificatePins>,
+    counters: Arc<Counters>,
 }
 
 #[derive(Clone)]
 enum Inner {
     #[cfg(not(feature = "__tls"))]
-    Http(HttpConnector),
+    Http(Transport),
     #[cfg(feature = "default-tls")]
-    DefaultTls(HttpConnector, TlsConnector),
-    #[cfg(feature = "rustls-tls")]
+    DefaultTls(Transport, TlsConnector),
+    #[cfg(feature = "__rustls")]
     RustlsTls {
-        http: HttpConnector,
+        http: Transport,
         tls: Arc<rustls::ClientConfig>,
         tls_proxy: Arc<rustls::ClientConfig>,
     },
@@ -60,63 +81,59 @@ enum Inner {
 
 impl Connector {
     #[cfg(not(feature = "__tls"))]
-    pub(crate) fn new<T>(
+    pub(crate) fn new(
+        resolver: DynResolver,
         proxies: Arc<Vec<Proxy>>,
-        local_addr: T,
+        tcp: TcpOptions,
         nodelay: bool,
-    ) -> crate::Result<Connector>
-    where
-        T: Into<Option<IpAddr>>,
-    {
-        let mut http = http_connector()?;
-        http.set_local_address(local_addr.into());
+    ) -> crate::Result<Connector> {
+        let mut http = http_connector(&resolver, tcp);
         http.set_nodelay(nodelay);
         Ok(Connector {
             inner: Inner::Http(http),
             proxies,
+            #[cfg(feature = "socks")]
+            resolver,
             timeout: None,
+            counters: Default::default(),
         })
     }
 
     #[cfg(feature = "default-tls")]
-    pub(crate) fn new_default_tls<T>(
-        tls: TlsConnectorBuilder,
+    pub(crate) fn new_default_tls(
+        resolver: DynResolver,
+        tls: TlsConnector,
         proxies: Arc<Vec<Proxy>>,
         user_agent: Option<HeaderValue>,
-        local_addr: T,
+        tcp: TcpOptions,
         nodelay: bool,
-    ) -> crate::Result<Connector>
-    where
-        T: Into<Option<IpAddr>>,
-    {
-        let tls = tls.build().map_err(crate::error::builder)?;
-
-        let mut http = http_connector()?;
-        http.set_local_address(local_addr.into());
+    ) -> crate::Result<Connector> {
+        let mut http = http_connector(&resolver, tcp);
         http.enforce_http(false);
 
         Ok(Connector {
             inner: Inner::DefaultTls(http, tls),
             proxies,
+            #[cfg(feature = "socks")]
+            resolver,
             timeout: None,
             nodelay,
             user_agent,
+            pins: Default::default(),
+            counters: Default::default(),
         })
     }
 
-    #[cfg(feature = "rustls-tls")]
-    pub(crate) fn new_rustls_tls<T>(
+    #[cfg(feature = "__rustls")]
+    pub(crate) fn new_rustls_tls(
+        resolver: DynResolver,
         tls: rustls::ClientConfig,
         proxies: Arc<Vec<Proxy>>,
         user_agent: Option<HeaderValue>,
-        local_addr: T,
+        tcp: TcpOptions,
         nodelay: bool,
-    ) -> crate::Result<Connector>
-    where
-        T: Into<Option<IpAddr>>,
-    {
-        let mut http = http_connector()?;
-        http.set_local_address(local_addr.into());
+    ) -> crate::Result<Connector> {
+        let mut http = http_connector(&resolver, tcp);
         http.enforce_http(false);
 
         let (tls, tls_proxy) = if proxies.is_empty() {
@@ -135,9 +152,13 @@ impl Connector {
                 tls_proxy,
             },
             proxies,
+            #[cfg(feature = "socks")]
+            resolver,
             timeout: None,
             nodelay,
             user_agent,
+            pins: Default::default(),
+            counters: Default::default(),
         })
     }
 
@@ -145,20 +166,56 @@ impl Connector {
         self.timeout = timeout;
     }
 
+    /// Reject the TLS connections to pinned hosts whose certificate doesn't
+    /// match a pin.
+    #[cfg(feature = "__tls")]
+    pub(crate) fn set_certificate_pins(&mut self, pins: CertificatePins) {
+        self.pins = Arc::new(pins);
+    }
+
+    /// The counters updated with the connections this opens.
+    pub(crate) fn counters(&self) -> Arc<Counters> {
+        self.counters.clone()
+    }
+
+    /// Establish connections with `connector` instead of TCP.
+    pub(crate) fn set_transport(&mut self, connector: CustomConnector) {
+        *self.transport_mut() = Transport::Custom(connector);
+    }
+
+    /// Set how long to wait for a connection to the preferred address
+    /// family before racing the other one.
+    pub(crate) fn set_happy_eyeballs_timeout(&mut self, timeout: Option<Duration>) {
+        if let Transport::Tcp(ref mut http) = self.transport_mut() {
+            http.set_happy_eyeballs_timeout(timeout);
+        }
+    }
+
+    fn transport_mut(&mut self) -> &mut Transport {
+        match self.inner {
+            #[cfg(not(feature = "__tls"))]
+            Inner::Http(ref mut http) => http,
+            #[cfg(feature = "default-tls")]
+            Inner::DefaultTls(ref mut http, _) => http,
+            #[cfg(feature = "__rustls")]
+            Inner::RustlsTls { ref mut http, .. } => http,
+        }
+    }
+
     #[cfg(feature = "socks")]
     async fn connect_socks(
         &self,
-        dst: Destination,
+        dst: Uri,
         proxy: ProxyScheme,
-    ) -> Result<(Conn, Connected), io::Error> {
+    ) -> Result<Conn, BoxError> {
         let dns = match proxy {
             ProxyScheme::Socks5 {
                 remote_dns: false, ..
-            } => socks::DnsResolve::Local,
+            } => socks::DnsResolve::Local(&self.resolver),
             ProxyScheme::Socks5 {
                 remote_dns: true, ..
             } => socks::DnsResolve::Proxy,
-            ProxyScheme::Http { .. } => {
+            ProxyScheme::Http { .. } | ProxyScheme::Https { .. } => {
                 unreachable!("connect_socks is only called for socks proxies");
             }
         };
@@ -166,42 +223,48 @@ impl Connector {
         match &self.inner {
             #[cfg(feature = "default-tls")]
             Inner::DefaultTls(_http, tls) => {
-                if dst.scheme() == "https" {
-                    use self::native_tls_async::TlsConnectorExt;
-
-                    let host = dst.host().to_owned();
-                    let socks_connecting = socks::connect(proxy, dst, dns);
-                    let (conn, connected) = socks::connect(proxy, dst, dns).await?;
-                    let tls_connector = tokio_tls::TlsConnector::from(tls.clone());
-                    let io = tls_connector
-                        .connect(&host, conn)
-                        .await
-                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-                    Ok((Box::new(io) as Conn, connected))
+                if dst.scheme() == Some(&Scheme::HTTPS) {
+                    let host = socks::host(&dst)?.to_owned();
+                    let conn = ConnectTimings::transport(socks::connect(proxy, dst, dns)).await?;
+                    let io = native_tls_conn::connect(tls, &host, conn).await?;
+                    self.pins.verify(&host, io.peer_certificate().as_deref())?;
+                    return Ok(Conn {
+                        inner: Box::new(io),
+                        is_proxy: false,
+                    });
                 }
             }
-            #[cfg(feature = "rustls-tls")]
+            #[cfg(feature = "__rustls")]
             Inner::RustlsTls { tls_proxy, .. } => {
-                if dst.scheme() == "https" {
+                if dst.scheme() == Some(&Scheme::HTTPS) {
                     use tokio_rustls::webpki::DNSNameRef;
                     use tokio_rustls::TlsConnector as RustlsConnector;
 
                     let tls = tls_proxy.clone();
-                    let host = dst.host().to_owned();
-                    let (conn, connected) = socks::connect(proxy, dst, dns);
+                    let host = socks::host(&dst)?.to_owned();
                     let dnsname = DNSNameRef::try_from_ascii_str(&host)
                         .map(|dnsname| dnsname.to_owned())
                         .map_err(|_| io::Error::new(io::ErrorKind::Other, "Invalid DNS Name"))?;
+                    let conn = ConnectTimings::transport(socks::connect(proxy, dst, dns)).await?;
                     let io = RustlsConnector::from(tls)
                         .connect(dnsname.as_ref(), conn)
                         .await
                         .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-                    Ok((Box::new(io) as Conn, connected))
+                    self.pins.verify(&host, rustls_peer_certificate(io.get_ref().1).as_deref())?;
+                    return Ok(Conn {
+                        inner: Box::new(RustlsTlsConn { inner: io }),
+                        is_proxy: false,
+                    });
                 }
             }
             #[cfg(not(feature = "__tls"))]
-            Inner::Http(_) => socks::connect(proxy, dst, dns),
+            Inner::Http(_) => (),
         }
+
+        ConnectTimings::transport(socks::connect(proxy, dst, dns)).await.map(|tcp| Conn {
+            inner: Box::new(tcp),
+            is_proxy: false,
+        })
     }
 
     async fn connect_with_maybe_proxy(
@@ -224,22 +287,29 @@ impl Connector {
 
                 http.set_nodelay(self.nodelay || (dst.scheme() == Some(&Scheme::HTTPS)));
 
-                let tls_connector = tokio_tls::TlsConnector::from(tls.clone());
-                let mut http = hyper_tls::HttpsConnector::from((http, tls_connector));
-                let io = http.call(dst).await?;
+                let io = http.call(dst.clone()).await?;
+                if dst.scheme() != Some(&Scheme::HTTPS) {
+                    return Ok(Conn {
+                        inner: Box::new(io),
+                        is_proxy,
+                    });
+                }
+
+                let host = dst.host().ok_or("no host in url")?;
+                let domain = host.trim_start_matches('[').trim_end_matches(']');
+                let io = native_tls_conn::connect(&tls, domain, io).await?;
                 //TODO: where's this at now?
-                //if let hyper_tls::MaybeHttpsStream::Https(_stream) = &io {
-                //    if !no_delay {
-                //        stream.set_nodelay(false)?;
-                //    }
+                //if !no_delay {
+                //    stream.set_nodelay(false)?;
                 //}
+                self.pins.verify(host, io.peer_certificate().as_deref())?;
 
                 Ok(Conn {
                     inner: Box::new(io),
                     is_proxy,
                 })
             }
-            #[cfg(feature = "rustls-tls")]
+            #[cfg(feature = "__rustls")]
             Inner::RustlsTls { http, tls, .. } => {
                 let mut http = http.clone();
 
@@ -249,11 +319,14 @@ impl Connector {
                 http.set_nodelay(self.nodelay || (dst.scheme() == Some(&Scheme::HTTPS)));
 
                 let mut http = hyper_rustls::HttpsConnector::from((http, tls.clone()));
-                let io = http.call(dst).await?;
+                let io = http.call(dst.clone()).await?;
 
                 if let hyper_rustls::MaybeHttpsStream::Https(stream) = &io {
+                    let (io, session) = stream.get_ref();
+                    if let Some(host) = dst.host() {
+                        self.pins.verify(host, rustls_peer_certificate(session).as_deref())?;
+                    }
                     if !self.nodelay {
-                        let (io, _) = stream.get_ref();
                         io.set_nodelay(false)?;
                     }
                 }
@@ -270,14 +343,18 @@ impl Connector {
         self,
         dst: Uri,
         proxy_scheme: ProxyScheme,
+        challenge_handler: Option<AuthHandler>,
     ) -> Result<Conn, BoxError> {
         log::trace!("proxy({:?}) intercepts {:?}", proxy_scheme, dst);
 
+        #[cfg(not(feature = "__tls"))]
+        let _ = challenge_handler;
+
         let (proxy_dst, _auth) = match proxy_scheme {
             ProxyScheme::Http { host, auth } => (into_uri(Scheme::HTTP, host), auth),
             ProxyScheme::Https { host, auth } => (into_uri(Scheme::HTTPS, host), auth),
             #[cfg(feature = "socks")]
-            ProxyScheme::Socks5 { .. } => return this.connect_socks(dst, proxy_scheme),
+            ProxyScheme::Socks5 { .. } => return self.connect_socks(dst, proxy_scheme).await,
         };
 
 
@@ -286,37 +363,33 @@ impl Connector {
 
         match &self.inner {
             #[cfg(feature = "default-tls")]
-            Inner::DefaultTls(http, tls) => {
+            Inner::DefaultTls(_http, tls) => {
                 if dst.scheme() == Some(&Scheme::HTTPS) {
                     let host = dst.host().to_owned();
                     let port = dst.port().map(|p| p.as_u16()).unwrap_or(443);
-                    let mut http = http.clone();
-                    http.set_nodelay(self.nodelay);
-                    let tls_connector = tokio_tls::TlsConnector::from(tls.clone());
-                    let mut http = hyper_tls::HttpsConnector::from((http, tls_connector));
-                    let conn = http.call(proxy_dst).await?;
+                    let mut http = ProxyConnector(self.clone());
                     log::trace!("tunneling HTTPS over proxy");
-                    let tunneled = tunnel(
-                        conn,
+                    let tunneled = tunnel_with_auth(
+                        &mut http,
+                        proxy_dst,
                         host
                             .ok_or(io::Error::new(io::ErrorKind::Other, "no host in url"))?
                             .to_string(),
                         port,
                         self.user_agent.clone(),
-                        auth
+                        auth,
+                        challenge_handler,
                     ).await?;
-                    let tls_connector = tokio_tls::TlsConnector::from(tls.clone());
-                    let io = tls_connector
-                        .connect(&host.ok_or(io::Error::new(io::ErrorKind::Other, "no host in url"))?, tunneled)
-                        .await
-                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    let host = host.ok_or(io::Error::new(io::ErrorKind::Other, "no host in url"))?;
+                    let io = native_tls_conn::connect(tls, host, tunneled).await?;
+                    self.pins.verify(host, io.peer_certificate().as_deref())?;
                     return Ok(Conn {
-                        inner: Box::new(NativeTlsConn { inner: io }),
+                        inner: Box::new(io),
                         is_proxy: false,
                     });
                 }
             }
-            #[cfg(feature = "rustls-tls")]
+            #[cfg(feature = "__rustls")]
             Inner::RustlsTls {
                 http,
                 tls,
@@ -334,17 +407,25 @@ impl Connector {
                     http.set_nodelay(self.nodelay);
                     let mut http = hyper_rustls::HttpsConnector::from((http, tls_proxy.clone()));
                     let tls = tls.clone();
-                    let conn = http.call(proxy_dst).await?;
                     log::trace!("tunneling HTTPS over proxy");
                     let maybe_dnsname = DNSNameRef::try_from_ascii_str(&host)
                         .map(|dnsname| dnsname.to_owned())
                         .map_err(|_| io::Error::new(io::ErrorKind::Other, "Invalid DNS Name"));
-                    let tunneled = tunnel(conn, host, port, self.user_agent.clone(), auth).await?;
+                    let tunneled = tunnel_with_auth(
+                        &mut http,
+                        proxy_dst,
+                        host.clone(),
+                        port,
+                        self.user_agent.clone(),
+                        auth,
+                        challenge_handler,
+                    ).await?;
                     let dnsname = maybe_dnsname?;
                     let io = RustlsConnector::from(tls)
                         .connect(dnsname.as_ref(), tunneled)
                         .await
                         .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    self.pins.verify(&host, rustls_peer_certificate(io.get_ref().1).as_deref())?;
 
                     return Ok(Conn {
                         inner: Box::new(RustlsTlsConn { inner: io }),
@@ -360,6 +441,26 @@ impl Connector {
     }
 }
 
+/// Connects to the proxy of a tunnel, making a TLS handshake with an
+/// `https` proxy.
+#[cfg(feature = "default-tls")]
+struct ProxyConnector(Connector);
+
+#[cfg(feature = "default-tls")]
+impl Service<Uri> for ProxyConnector {
+    type Response = Conn;
+    type Error = BoxError;
+    type Future = Connecting;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        Box::pin(self.0.clone().connect_with_maybe_proxy(dst, true))
+    }
+}
+
 fn into_uri(scheme: Scheme, host: Authority) -> Uri {
     // TODO: Should the `http` crate get `From<(Scheme, Authority)> for Uri`?
     http::Uri::builder()
@@ -370,16 +471,316 @@ fn into_uri(scheme: Scheme, host: Authority) -> Uri {
         .expect("scheme and authority is valid Uri")
 }
 
-//#[cfg(feature = "trust-dns")]
-//fn http_connector() -> crate::Result<HttpConnector> {
-//    TrustDnsResolver::new()
-//        .map(HttpConnector::new_with_resolver)
-//        .map_err(crate::error::dns_system_conf)
-//}
+/// Options for the TCP sockets a `Connector` opens.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TcpOptions {
+    pub(crate) local_address: Option<IpAddr>,
+    pub(crate) keepalive: Option<Duration>,
+    pub(crate) send_buffer_size: Option<usize>,
+    pub(crate) recv_buffer_size: Option<usize>,
+    #[cfg(target_os = "linux")]
+    pub(crate) interface: Option<CString>,
+}
+
+fn http_connector(resolver: &DynResolver, tcp: TcpOptions) -> Transport {
+    #[cfg(target_os = "linux")]
+    {
+        if tcp.interface.is_some() {
+            return Transport::Bound(BoundConnector {
+                resolver: resolver.clone(),
+                tcp: Arc::new(tcp),
+                nodelay: false,
+            });
+        }
+    }
+
+    let mut http = HttpConnector::new_with_resolver(resolver.clone());
+    http.set_local_address(tcp.local_address);
+    http.set_keepalive(tcp.keepalive);
+    http.set_send_buffer_size(tcp.send_buffer_size);
+    http.set_recv_buffer_size(tcp.recv_buffer_size);
+    Transport::Tcp(http)
+}
+
+/// Opens TCP connections bound to a network interface, which
+/// `HttpConnector` can't do.
+///
+/// The resolved addresses are tried in turn, without racing address
+/// families.
+#[cfg(target_os = "linux")]
+#[derive(Clone)]
+struct BoundConnector {
+    resolver: DynResolver,
+    tcp: Arc<TcpOptions>,
+    nodelay: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl BoundConnector {
+    async fn connect(self, dst: Uri) -> Result<TcpStream, BoxError> {
+        let host = dst.host().ok_or("URI has no host")?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = match dst.port_u16() {
+            Some(port) => port,
+            None if dst.scheme() == Some(&Scheme::HTTPS) => 443,
+            None => 80,
+        };
+
+        let addrs = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![ip],
+            Err(_) => self.resolver.resolve(host.parse()?).await?.collect(),
+        };
+
+        let mut last_err = None;
+        for ip in addrs {
+            match self.connect_addr(SocketAddr::new(ip, port)).await {
+                Ok(tcp) => return Ok(tcp),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        match last_err {
+            Some(err) => Err(err.into()),
+            None => Err("no addresses to connect to".into()),
+        }
+    }
+
+    async fn connect_addr(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        use socket2::{Domain, Protocol, Socket, Type};
+
+        let domain = if addr.is_ipv4() {
+            Domain::ipv4()
+        } else {
+            Domain::ipv6()
+        };
+        let socket = Socket::new(domain, Type::stream(), Some(Protocol::tcp()))?;
+        socket.bind_device(self.tcp.interface.as_deref())?;
+        match self.tcp.local_address {
+            Some(ip) if ip.is_ipv4() == addr.is_ipv4() => {
+                socket.bind(&SocketAddr::new(ip, 0).into())?;
+            }
+            _ => (),
+        }
+        if let Some(size) = self.tcp.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.tcp.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+
+        let tcp = TcpStream::connect_std(socket.into_tcp_stream(), &addr).await?;
+        tcp.set_nodelay(self.nodelay)?;
+        tcp.set_keepalive(self.tcp.keepalive)?;
+        Ok(tcp)
+    }
+}
+
+/// A connector for the underlying transport, set with
+/// `ClientBuilder::connector`.
+///
+/// TLS and proxy tunnels are layered on top of the connections it returns,
+/// just like on top of TCP connections.
+#[derive(Clone)]
+pub(crate) struct CustomConnector {
+    connect: Arc<dyn Fn(Uri) -> CustomConnecting + Send + Sync>,
+}
+
+type CustomConnecting = Pin<Box<dyn Future<Output = Result<BoxedIo, BoxError>> + Send>>;
+
+impl CustomConnector {
+    pub(crate) fn new<C>(connector: C) -> CustomConnector
+    where
+        C: Service<Uri> + Clone + Send + Sync + 'static,
+        C::Response: AsyncRead + AsyncWrite + Send + Sync + Unpin + 'static,
+        C::Future: Send + 'static,
+        C::Error: Into<BoxError>,
+    {
+        let connect = move |dst: Uri| -> CustomConnecting {
+            let mut connector = connector.clone();
+            Box::pin(async move {
+                futures_util::future::poll_fn(|cx| connector.poll_ready(cx))
+                    .await
+                    .map_err(Into::into)?;
+                let io = connector.call(dst).await.map_err(Into::into)?;
+                Ok(Box::new(io) as BoxedIo)
+            })
+        };
+        CustomConnector {
+            connect: Arc::new(connect),
+        }
+    }
+}
+
+/// Connects to a Unix domain socket whatever the destination, set with
+/// `ClientBuilder::unix_socket`.
+#[cfg(unix)]
+#[derive(Clone)]
+pub(crate) struct UnixConnector {
+    path: Arc<Path>,
+}
+
+#[cfg(unix)]
+impl UnixConnector {
+    pub(crate) fn new(path: &Path) -> UnixConnector {
+        UnixConnector { path: path.into() }
+    }
+}
+
+#[cfg(unix)]
+impl Service<Uri> for UnixConnector {
+    type Response = UnixStream;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<UnixStream>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _dst: Uri) -> Self::Future {
+        let path = self.path.clone();
+        Box::pin(async move { UnixStream::connect(&*path).await })
+    }
+}
+
+trait AsyncIo: AsyncRead + AsyncWrite {}
+impl<T: AsyncRead + AsyncWrite> AsyncIo for T {}
+
+type BoxedIo = Box<dyn AsyncIo + Send + Sync + Unpin + 'static>;
+
+/// The connections TLS and proxy tunnels are established over.
+#[derive(Clone)]
+enum Transport {
+    Tcp(HttpConnector),
+    #[cfg(target_os = "linux")]
+    Bound(BoundConnector),
+    Custom(CustomConnector),
+}
+
+impl Transport {
+    fn set_nodelay(&mut self, nodelay: bool) {
+        match self {
+            Transport::Tcp(http) => http.set_nodelay(nodelay),
+            #[cfg(target_os = "linux")]
+            Transport::Bound(bound) => bound.nodelay = nodelay,
+            Transport::Custom(_) => (),
+        }
+    }
+
+    #[cfg(feature = "__tls")]
+    fn enforce_http(&mut self, enforce: bool) {
+        if let Transport::Tcp(ref mut http) = self {
+            http.enforce_http(enforce);
+        }
+    }
+}
+
+impl Service<Uri> for Transport {
+    type Response = TransportConn;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<TransportConn, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            Transport::Tcp(http) => http.poll_ready(cx).map_err(Into::into),
+            #[cfg(target_os = "linux")]
+            Transport::Bound(_) => Poll::Ready(Ok(())),
+            Transport::Custom(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        match self {
+            Transport::Tcp(http) => {
+                let connecting = ConnectTimings::transport(http.call(dst));
+                Box::pin(async move { Ok(TransportConn::Tcp(connecting.await?)) })
+            }
+            #[cfg(target_os = "linux")]
+            Transport::Bound(bound) => {
+                let connecting = ConnectTimings::transport(bound.clone().connect(dst));
+                Box::pin(async move { Ok(TransportConn::Tcp(connecting.await?)) })
+            }
+            Transport::Custom(custom) => {
+                let connecting = ConnectTimings::transport((custom.connect)(dst));
+                Box::pin(async move { Ok(TransportConn::Custom(connecting.await?)) })
+            }
+        }
+    }
+}
+
+enum TransportConn {
+    Tcp(TcpStream),
+    Custom(BoxedIo),
+}
+
+impl TransportConn {
+    #[cfg(feature = "__rustls")]
+    fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        match self {
+            TransportConn::Tcp(tcp) => tcp.set_nodelay(nodelay),
+            TransportConn::Custom(_) => Ok(()),
+        }
+    }
+}
+
+impl Connection for TransportConn {
+    fn connected(&self) -> Connected {
+        match self {
+            TransportConn::Tcp(tcp) => tcp.connected(),
+            TransportConn::Custom(_) => Connected::new(),
+        }
+    }
+}
+
+impl AsyncRead for TransportConn {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8]
+    ) -> Poll<tokio::io::Result<usize>> {
+        match self.get_mut() {
+            TransportConn::Tcp(tcp) => Pin::new(tcp).poll_read(cx, buf),
+            TransportConn::Custom(io) => Pin::new(io).poll_read(cx, buf),
+        }
+    }
 
-//#[cfg(not(feature = "trust-dns"))]
-fn http_connector() -> crate::Result<HttpConnector> {
-    Ok(HttpConnector::new())
+    unsafe fn prepare_uninitialized_buffer(
+        &self,
+        buf: &mut [MaybeUninit<u8>]
+    ) -> bool {
+        match self {
+            TransportConn::Tcp(tcp) => tcp.prepare_uninitialized_buffer(buf),
+            TransportConn::Custom(io) => io.prepare_uninitialized_buffer(buf),
+        }
+    }
+}
+
+impl AsyncWrite for TransportConn {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8]
+    ) -> Poll<Result<usize, tokio::io::Error>> {
+        match self.get_mut() {
+            TransportConn::Tcp(tcp) => Pin::new(tcp).poll_write(cx, buf),
+            TransportConn::Custom(io) => Pin::new(io).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), tokio::io::Error>> {
+        match self.get_mut() {
+            TransportConn::Tcp(tcp) => Pin::new(tcp).poll_flush(cx),
+            TransportConn::Custom(io) => Pin::new(io).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context
+    ) -> Poll<Result<(), tokio::io::Error>> {
+        match self.get_mut() {
+            TransportConn::Tcp(tcp) => Pin::new(tcp).poll_shutdown(cx),
+            TransportConn::Custom(io) => Pin::new(io).poll_shutdown(cx),
+        }
+    }
 }
 
 
@@ -410,22 +811,61 @@ impl Service<Uri> for Connector
 
     fn call(&mut self, dst: Uri) -> Self::Future {
         let timeout = self.timeout;
+        let counters = self.counters.clone();
         for prox in self.proxies.iter() {
             if let Some(proxy_scheme) = prox.intercept(&dst) {
-                return Box::pin(with_timeout(
-                    self.clone().connect_via_proxy(dst, proxy_scheme),
-                    timeout,
+                let tls = is_tls(&dst, Some(&proxy_scheme));
+                return Box::pin(counted(
+                    with_timeout(
+                        self.clone().connect_via_proxy(
+                            dst,
+                            proxy_scheme,
+                            prox.challenge_handler().cloned(),
+                        ),
+                        timeout,
+                    ),
+                    counters,
+                    tls,
                 ));
             }
         }
 
-        Box::pin(with_timeout(
-            self.clone().connect_with_maybe_proxy(dst, false),
-            timeout,
+        let tls = is_tls(&dst, None);
+        Box::pin(counted(
+            with_timeout(self.clone().connect_with_maybe_proxy(dst, false), timeout),
+            counters,
+            tls,
         ))
     }
 }
 
+/// Whether connecting to `dst` makes a TLS handshake, with `dst` or with an
+/// `https` proxy.
+fn is_tls(dst: &Uri, proxy: Option<&ProxyScheme>) -> bool {
+    if !cfg!(feature = "__tls") {
+        return false;
+    }
+    if let Some(ProxyScheme::Https { .. }) = proxy {
+        return true;
+    }
+    dst.scheme() == Some(&Scheme::HTTPS)
+}
+
+async fn counted<F>(f: F, counters: Arc<Counters>, tls: bool) -> Result<Conn, BoxError>
+where
+    F: Future<Output = Result<Conn, BoxError>>,
+{
+    let (conn, timings) = ConnectTimings::collect(f, tls).await;
+    let mut conn = conn?;
+    counters.connection_created();
+    conn.inner = Box::new(CountedConn {
+        inner: conn.inner,
+        counters,
+        info: ConnInfo::new(timings),
+    });
+    Ok(conn)
+}
+
 pub(crate) trait AsyncConn: AsyncRead + AsyncWrite + Connection {}
 impl<T: AsyncRead + AsyncWrite + Connection> AsyncConn for T {}
 
@@ -507,6 +947,62 @@ impl AsyncWrite for Conn {
     }
 }
 
+/// Counts the bytes sent and received over a connection, and passes its
+/// `ConnInfo` to the `Client` through the response extensions.
+struct CountedConn {
+    inner: Box<dyn AsyncConn + Send + Sync + Unpin + 'static>,
+    counters: Arc<Counters>,
+    info: ConnInfo,
+}
+
+impl Connection for CountedConn {
+    fn connected(&self) -> Connected {
+        self.inner.connected().extra(self.info.clone())
+    }
+}
+
+impl AsyncRead for CountedConn {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8]
+    ) -> Poll<tokio::io::Result<usize>> {
+        let n = futures_core::ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+        self.counters.bytes_received(n);
+        Poll::Ready(Ok(n))
+    }
+
+    unsafe fn prepare_uninitialized_buffer(
+        &self,
+        buf: &mut [MaybeUninit<u8>]
+    ) -> bool {
+        self.inner.prepare_uninitialized_buffer(buf)
+    }
+}
+
+impl AsyncWrite for CountedConn {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8]
+    ) -> Poll<Result<usize, tokio::io::Error>> {
+        let n = futures_core::ready!(Pin::new(&mut self.inner).poll_write(cx, buf))?;
+        self.counters.bytes_sent(n);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), tokio::io::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context
+    ) -> Poll<Result<(), tokio::io::Error>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
 pub(crate) type Connecting =
     Pin<Box<dyn Future<Output = Result<Conn, BoxError>> + Send>>;
 
@@ -577,17 +1073,126 @@ where
                 ));
             }
         // else read more
-        } else if recvd.starts_with(b"HTTP/1.1 407") {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "proxy authentication required",
-            ));
+        } else if recvd.starts_with(b"HTTP/1.1 407") || recvd.starts_with(b"HTTP/1.0 407") {
+            if let Some(end) = recvd.windows(4).position(|w| w == b"\r\n\r\n") {
+                let uri = format!("{}:{}", host, port);
+                let headers = parse_headers(&recvd[..end]);
+                let challenge = ProxyChallenge::new(Method::CONNECT, uri, &headers);
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    TunnelAuthRequired(challenge),
+                ));
+            }
+            if pos == buf.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "proxy headers too long for tunnel",
+                ));
+            }
+        // else read more
         } else {
             return Err(io::Error::new(io::ErrorKind::Other, "unsuccessful tunnel"));
         }
     }
 }
 
+/// Establishes a tunnel through the proxy at `proxy_dst`, answering a `407`
+/// challenge once with `challenge_handler`, over a new connection.
+#[cfg(feature = "__tls")]
+async fn tunnel_with_auth<S>(
+    connector: &mut S,
+    proxy_dst: Uri,
+    host: String,
+    port: u16,
+    user_agent: Option<HeaderValue>,
+    mut auth: Option<HeaderValue>,
+    challenge_handler: Option<AuthHandler>,
+) -> Result<S::Response, BoxError>
+where
+    S: Service<Uri>,
+    S::Response: AsyncRead + AsyncWrite + Unpin,
+    S::Error: Into<BoxError>,
+{
+    let mut challenged = false;
+    loop {
+        let conn = connector.call(proxy_dst.clone()).await.map_err(Into::into)?;
+        // Boxed, since its read buffer would otherwise be kept inline in
+        // the already large connecting future.
+        let tunneling = Box::pin(tunnel(conn, host.clone(), port, user_agent.clone(), auth.take()));
+        let err = match ConnectTimings::tunnel(tunneling).await {
+            Ok(tunneled) => return Ok(tunneled),
+            Err(err) => err,
+        };
+
+        let required = err
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<TunnelAuthRequired>());
+        let answer = match (required, &challenge_handler) {
+            (Some(required), Some(handler)) if !challenged => handler.answer(&required.0),
+            _ => None,
+        };
+        match answer {
+            Some(answer) => {
+                log::debug!("answering proxy challenge for tunnel to {}:{}", host, port);
+                challenged = true;
+                auth = Some(answer);
+            }
+            None => return Err(err.into()),
+        }
+    }
+}
+
+/// Parses the header lines of a proxy response, skipping its status line.
+#[cfg(feature = "__tls")]
+fn parse_headers(head: &[u8]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for line in head.split(|&b| b == b'\n').skip(1) {
+        let colon = match line.iter().position(|&b| b == b':') {
+            Some(colon) => colon,
+            None => continue,
+        };
+        let name = HeaderName::from_bytes(&line[..colon]);
+        let value = HeaderValue::from_bytes(trim_ows(&line[colon + 1..]));
+        if let (Ok(name), Ok(value)) = (name, value) {
+            headers.append(name, value);
+        }
+    }
+    headers
+}
+
+/// Trims the whitespace around a header value, and the `\r` ending its line.
+#[cfg(feature = "__tls")]
+fn trim_ows(s: &[u8]) -> &[u8] {
+    let is_ows = |b: &u8| *b == b' ' || *b == b'\t' || *b == b'\r';
+    let start = s.iter().position(|b| !is_ows(b)).unwrap_or(s.len());
+    let end = s.iter().rposition(|b| !is_ows(b)).map_or(start, |i| i + 1);
+    &s[start..end]
+}
+
+/// A `407 Proxy Authentication Required` response to a `CONNECT` request.
+#[cfg(feature = "__tls")]
+#[derive(Debug)]
+struct TunnelAuthRequired(ProxyChallenge);
+
+#[cfg(feature = "__tls")]
+impl fmt::Display for TunnelAuthRequired {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("proxy authentication required")
+    }
+}
+
+#[cfg(feature = "__tls")]
+impl StdError for TunnelAuthRequired {}
+
+/// The DER certificate a server presented for itself.
+#[cfg(feature = "__rustls")]
+fn rustls_peer_certificate(session: &rustls::ClientSession) -> Option<Vec<u8>> {
+    use rustls::Session;
+
+    let certs = session.get_peer_certificates()?;
+    certs.into_iter().next().map(|cert| cert.0)
+}
+
 #[cfg(feature = "__tls")]
 fn tunnel_eof() -> io::Error {
     io::Error::new(
@@ -598,24 +1203,88 @@ fn tunnel_eof() -> io::Error {
 
 #[cfg(feature = "default-tls")]
 mod native_tls_conn {
+    use std::io::{self, Read, Write};
     use std::mem::MaybeUninit;
-    use std::{pin::Pin, task::{Context, Poll}};
-    use bytes::{Buf, BufMut};
+    use std::{pin::Pin, task::{Context, Poll, Waker}};
     use hyper::client::connect::{Connected, Connection};
-    use pin_project_lite::pin_project;
+    use native_tls_crate::{HandshakeError, MidHandshakeTlsStream, TlsConnector, TlsStream};
     use tokio::io::{AsyncRead, AsyncWrite};
-    use tokio_tls::TlsStream;
 
+    /// A TLS connection over `T`, made with native-tls.
+    ///
+    /// This drives the blocking `native_tls::TlsStream` with the async I/O of
+    /// `T`, like `tokio_tls` does, but keeps it reachable to read the peer
+    /// certificate.
+    pub(super) struct NativeTlsConn<T> {
+        inner: TlsStream<AllowStd<T>>,
+    }
 
-    pin_project! {
-        pub(super) struct NativeTlsConn<T> {
-            #[pin] pub(super) inner: TlsStream<T>,
+    /// Makes a TLS handshake with `domain` over `io`.
+    pub(super) async fn connect<T>(
+        tls: &TlsConnector,
+        domain: &str,
+        io: T,
+    ) -> io::Result<NativeTlsConn<T>>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut start = Some(AllowStd { inner: io, waker: None });
+        let mut mid: Option<MidHandshakeTlsStream<AllowStd<T>>> = None;
+        futures_util::future::poll_fn(|cx| {
+            let res = match mid.take() {
+                Some(mut handshaking) => {
+                    handshaking.get_mut().waker = Some(cx.waker().clone());
+                    handshaking.handshake()
+                }
+                None => {
+                    let mut io = start.take().expect("handshake polled after completion");
+                    io.waker = Some(cx.waker().clone());
+                    tls.connect(domain, io)
+                }
+            };
+            match res {
+                Ok(mut inner) => {
+                    inner.get_mut().waker = None;
+                    Poll::Ready(Ok(NativeTlsConn { inner }))
+                }
+                Err(HandshakeError::WouldBlock(mut handshaking)) => {
+                    handshaking.get_mut().waker = None;
+                    mid = Some(handshaking);
+                    Poll::Pending
+                }
+                Err(HandshakeError::Failure(e)) => {
+                    Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, e)))
+                }
+            }
+        })
+        .await
+    }
+
+    impl<T: AsyncRead + AsyncWrite + Unpin> NativeTlsConn<T> {
+        /// The DER certificate the server presented for itself.
+        pub(super) fn peer_certificate(&self) -> Option<Vec<u8>> {
+            let cert = self.inner.peer_certificate().ok()??;
+            cert.to_der().ok()
+        }
+
+        fn with_context<R>(
+            &mut self,
+            cx: &mut Context,
+            f: impl FnOnce(&mut TlsStream<AllowStd<T>>) -> io::Result<R>,
+        ) -> Poll<io::Result<R>> {
+            self.inner.get_mut().waker = Some(cx.waker().clone());
+            let res = f(&mut self.inner);
+            self.inner.get_mut().waker = None;
+            match res {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+                res => Poll::Ready(res),
+            }
         }
     }
 
-    impl<T: Connection + AsyncRead + AsyncWrite + Unpin> Connection for NativeTlsConn<T> {
+    impl<T: Connection> Connection for NativeTlsConn<T> {
         fn connected(&self) -> Connected {
-            self.inner.get_ref().connected()
+            self.inner.get_ref().inner.connected()
         }
     }
 
@@ -625,27 +1294,16 @@ mod native_tls_conn {
             cx: &mut Context,
             buf: &mut [u8]
         ) -> Poll<tokio::io::Result<usize>> {
-            let this = self.project();
-            AsyncRead::poll_read(this.inner, cx, buf)
+            self.get_mut().with_context(cx, |tls| tls.read(buf))
         }
 
         unsafe fn prepare_uninitialized_buffer(
             &self,
-            buf: &mut [MaybeUninit<u8>]
+            _buf: &mut [MaybeUninit<u8>]
         ) -> bool {
-            self.inner.prepare_uninitialized_buffer(buf)
-        }
-
-        fn poll_read_buf<B: BufMut>(
-            self: Pin<&mut Self>,
-            cx: &mut Context,
-            buf: &mut B
-        ) -> Poll<tokio::io::Result<usize>>
-            where
-                Self: Sized
-        {
-            let this = self.project();
-            AsyncRead::poll_read_buf(this.inner, cx, buf)
+            // The buffer is filled in by native-tls, with bytes decrypted
+            // from `T`, so there's no need to zero it.
+            false
         }
     }
 
@@ -655,36 +1313,66 @@ mod native_tls_conn {
             cx: &mut Context,
             buf: &[u8]
         ) -> Poll<Result<usize, tokio::io::Error>> {
-            let this = self.project();
-            AsyncWrite::poll_write(this.inner, cx, buf)
+            self.get_mut().with_context(cx, |tls| tls.write(buf))
         }
 
         fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), tokio::io::Error>> {
-            let this = self.project();
-            AsyncWrite::poll_flush(this.inner, cx)
+            self.get_mut().with_context(cx, |tls| tls.flush())
         }
 
         fn poll_shutdown(
             self: Pin<&mut Self>,
             cx: &mut Context
         ) -> Poll<Result<(), tokio::io::Error>> {
-            let this = self.project();
-            AsyncWrite::poll_shutdown(this.inner, cx)
+            self.get_mut().with_context(cx, |tls| tls.shutdown())
         }
+    }
 
-        fn poll_write_buf<B: Buf>(
-            self: Pin<&mut Self>,
-            cx: &mut Context,
-            buf: &mut B
-        ) -> Poll<Result<usize, tokio::io::Error>> where
-            Self: Sized {
-            let this = self.project();
-            AsyncWrite::poll_write_buf(this.inner, cx, buf)
+    /// Adapts async I/O to the blocking `Read` and `Write` native-tls
+    /// expects, returning `WouldBlock` when it isn't ready.
+    ///
+    /// The waker of the task polling the connection is set for the duration
+    /// of each call into native-tls.
+    struct AllowStd<T> {
+        inner: T,
+        waker: Option<Waker>,
+    }
+
+    impl<T: Unpin> AllowStd<T> {
+        fn with_context<R>(
+            &mut self,
+            f: impl FnOnce(&mut Context, Pin<&mut T>) -> Poll<io::Result<R>>,
+        ) -> io::Result<R> {
+            let waker = match self.waker {
+                Some(ref waker) => waker,
+                None => return Err(io::ErrorKind::WouldBlock.into()),
+            };
+            let mut cx = Context::from_waker(waker);
+            match f(&mut cx, Pin::new(&mut self.inner)) {
+                Poll::Ready(res) => res,
+                Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+            }
+        }
+    }
+
+    impl<T: AsyncRead + Unpin> Read for AllowStd<T> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.with_context(|cx, io| io.poll_read(cx, buf))
+        }
+    }
+
+    impl<T: AsyncWrite + Unpin> Write for AllowStd<T> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.with_context(|cx, io| io.poll_write(cx, buf))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.with_context(|cx, io| io.poll_flush(cx))
         }
     }
 }
 
-#[cfg(feature = "rustls-tls")]
+#[cfg(feature = "__rustls")]
 mod rustls_tls_conn {
     use rustls::Session;
     use std::mem::MaybeUninit;
@@ -781,34 +1469,46 @@ mod rustls_tls_conn {
 mod socks {
     use std::io;
 
-    use futures::{future, Future};
-    use hyper::client::connect::{Connected, Destination};
-    use socks::Socks5Stream;
-    use std::net::ToSocketAddrs;
-    use tokio::{net::TcpStream, reactor};
+    use http::Uri;
+    use http::uri::Scheme;
+    use tokio::net::TcpStream;
+    use tokio_socks::tcp::Socks5Stream;
 
-    use super::Connecting;
+    use crate::dns::{DynResolver, Name};
+    use crate::error::BoxError;
     use crate::proxy::ProxyScheme;
 
-    pub(super) enum DnsResolve {
-        Local,
+    pub(super) enum DnsResolve<'a> {
+        Local(&'a DynResolver),
         Proxy,
     }
 
+    /// The host of `dst`, without the brackets around IPv6 literals.
+    pub(super) fn host(dst: &Uri) -> Result<&str, io::Error> {
+        let host = dst
+            .host()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "no host in url"))?;
+        Ok(host.trim_start_matches('[').trim_end_matches(']'))
+    }
+
     pub(super) async fn connect(
         proxy: ProxyScheme,
-        dst: Destination,
-        dns: DnsResolve,
-    ) -> Result<super::Conn, BoxError> {
+        dst: Uri,
+        dns: DnsResolve<'_>,
+    ) -> Result<TcpStream, BoxError> {
         let https = dst.scheme() == Some(&Scheme::HTTPS);
-        let original_host = dst.host().to_owned();
-        let mut host = original_host.clone();
-        let port = dst.port().unwrap_or_else(|| if https { 443 } else { 80 });
+        let mut host = host(&dst)?.to_owned();
+        let port = match dst.port() {
+            Some(p) => p.as_u16(),
+            None if https => 443u16,
+            _ => 80u16,
+        };
 
-        if let DnsResolve::Local = dns {
-            let maybe_new_target = (host.as_str(), port).to_socket_addrs()?.next();
+        if let DnsResolve::Local(resolver) = dns {
+            let name = host.parse::<Name>()?;
+            let maybe_new_target = resolver.resolve(name).await?.next();
             if let Some(new_target) = maybe_new_target {
-                host = new_target.ip().to_string();
+                host = new_target.to_string();
             }
         }
 
@@ -817,7 +1517,6 @@ mod socks {
             _ => unreachable!(),
         };
 
-        // Get a Tokio TcpStream
         let stream = if let Some((username, password)) = auth {
             Socks5Stream::connect_with_password(
                 socket_addr,
@@ -826,21 +1525,23 @@ mod socks {
                 &password,
             )
             .await
+            .map_err(|e| format!("socks connect error: {}", e))?
         } else {
-            let s = Socks5Stream::connect(socket_addr, (host.as_str(), port)).await;
-            TcpStream::from_std(s.into_inner(), &reactor::Handle::default())
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+            Socks5Stream::connect(socket_addr, (host.as_str(), port))
+                .await
+                .map_err(|e| format!("socks connect error: {}", e))?
         };
 
-        Ok((Box::new(s) as super::Conn, Connected::new()))
+        Ok(stream.into_inner())
     }
 }
 
 #[cfg(feature = "__tls")]
 #[cfg(test)]
 mod tests {
-    use super::tunnel;
+    use super::{tunnel, tunnel_with_auth};
     use crate::proxy;
+    use crate::proxy_auth::{AuthHandler, Credentials};
     use std::io::{Read, Write};
     use std::net::TcpListener;
     use std::thread;
@@ -985,4 +1686,71 @@ mod tests {
 
         rt.block_on(f).unwrap();
     }
+
+    #[test]
+    fn test_tunnel_digest_challenge() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let proxy = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in &[
+                &b"\
+                HTTP/1.1 407 Proxy Authentication Required\r\n\
+                Proxy-Authenticate: Digest realm=\"proxy\", nonce=\"abc\", qop=\"auth\"\r\n\
+                Content-Length: 0\r\n\
+                \r\n\
+                "[..],
+                TUNNEL_OK,
+            ] {
+                let (mut sock, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 4096];
+                let n = sock.read(&mut buf).unwrap();
+                requests.push(String::from_utf8(buf[..n].to_vec()).unwrap());
+                sock.write_all(response).unwrap();
+            }
+            requests
+        });
+
+        let mut rt = runtime::Builder::new().basic_scheduler().enable_all().build().expect("new rt");
+        let f = async move {
+            let mut http = hyper::client::HttpConnector::new();
+            let proxy_dst = format!("http://{}", addr).parse().unwrap();
+            let credentials = Credentials::new("Aladdin", "open sesame");
+            let handler = AuthHandler::new(move |challenge| credentials.answer(challenge));
+            tunnel_with_auth(&mut http, proxy_dst, "hyper.rs".into(), 443, ua(), None, Some(handler))
+                .await
+        };
+
+        rt.block_on(f).unwrap();
+
+        let requests = proxy.join().unwrap();
+        assert!(!requests[0].contains("Proxy-Authorization"));
+        assert!(requests[1].starts_with("CONNECT hyper.rs:443 HTTP/1.1\r\n"));
+        assert!(requests[1].contains("Proxy-Authorization: Digest username=\"Aladdin\", realm=\"proxy\", nonce=\"abc\", uri=\"hyper.rs:443\""));
+    }
+
+    #[test]
+    fn test_tunnel_challenge_unanswered() {
+        let addr = mock_tunnel!(
+            b"\
+            HTTP/1.1 407 Proxy Authentication Required\r\n\
+            Proxy-Authenticate: NTLM\r\n\
+            \r\n\
+        "
+        );
+
+        let mut rt = runtime::Builder::new().basic_scheduler().enable_all().build().expect("new rt");
+        let f = async move {
+            let mut http = hyper::client::HttpConnector::new();
+            let proxy_dst = format!("http://{}", addr).parse().unwrap();
+            let host = addr.ip().to_string();
+            let credentials = Credentials::new("Aladdin", "open sesame");
+            let handler = AuthHandler::new(move |challenge| credentials.answer(challenge));
+            tunnel_with_auth(&mut http, proxy_dst, host, addr.port(), ua(), None, Some(handler)).await
+        };
+
+        let error = rt.block_on(f).unwrap_err();
+        assert_eq!(error.to_string(), "proxy authentication required");
+    }
 }
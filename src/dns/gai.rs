@@ -0,0 +1,24 @@
+use hyper::client::connect::dns::GaiResolver as HyperGaiResolver;
+use hyper::service::Service;
+
+use super::{Addrs, Name, Resolve, Resolving};
+
+/// The default resolver, using `getaddrinfo` on a blocking threadpool.
+#[derive(Debug)]
+pub(crate) struct GaiResolver(HyperGaiResolver);
+
+impl GaiResolver {
+    pub(crate) fn new() -> Self {
+        GaiResolver(HyperGaiResolver::new())
+    }
+}
+
+impl Resolve for GaiResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let mut gai = self.0.clone();
+        Box::pin(async move {
+            let addrs = gai.call(name.0).await?;
+            Ok(Box::new(addrs) as Addrs)
+        })
+    }
+}
@@ -0,0 +1,225 @@
+//! DNS resolution
+//!
+//! By default, a `Client` resolves host names with the system's
+//! `getaddrinfo`, run on a blocking threadpool. With the `trust-dns` feature
+//! enabled, an async trust-dns resolver reading the system configuration is
+//! used instead. A custom resolver can be
+//! used instead by implementing [`Resolve`](Resolve) and passing it to
+//! `ClientBuilder::dns_resolver`.
+//!
+//! The resolver is used for every connection a `Client` makes, whether to
+//! the destination itself or to a proxy. The addresses it returns are then
+//! ordered or filtered by the [`AddressFamily`](AddressFamily) set with
+//! `ClientBuilder::address_family`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use hyper::client::connect::dns::Name as HyperName;
+use hyper::service::Service;
+
+use crate::error::BoxError;
+use crate::timings::ConnectTimings;
+
+#[cfg(not(feature = "trust-dns"))]
+pub(crate) use self::gai::GaiResolver;
+#[cfg(feature = "trust-dns")]
+pub(crate) use self::trust_dns::TrustDnsResolver;
+
+#[cfg(not(feature = "trust-dns"))]
+mod gai;
+#[cfg(feature = "trust-dns")]
+mod trust_dns;
+
+/// Alias for an `Iterator` trait object over `IpAddr`.
+pub type Addrs = Box<dyn Iterator<Item = IpAddr> + Send>;
+
+/// Alias for the `Future` type returned by a DNS resolver.
+pub type Resolving = Pin<Box<dyn Future<Output = Result<Addrs, BoxError>> + Send>>;
+
+/// Trait for customizing DNS resolution in reqwest.
+///
+/// # Example
+///
+/// ```
+/// use std::net::{IpAddr, Ipv4Addr};
+/// use std::sync::Arc;
+/// use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+///
+/// struct Localhost;
+///
+/// impl Resolve for Localhost {
+///     fn resolve(&self, _name: Name) -> Resolving {
+///         let addrs: Addrs = Box::new(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)].into_iter());
+///         Box::pin(async move { Ok(addrs) })
+///     }
+/// }
+///
+/// let client = reqwest::Client::builder()
+///     .dns_resolver(Arc::new(Localhost))
+///     .build()
+///     .unwrap();
+/// # drop(client);
+/// ```
+pub trait Resolve: Send + Sync {
+    /// Performs DNS resolution on a `Name`.
+    ///
+    /// The return type is a future containing an iterator of `IpAddr`. The
+    /// port used to connect is always taken from the request's URL.
+    fn resolve(&self, name: Name) -> Resolving;
+}
+
+/// A name that must be resolved to addresses.
+#[derive(Debug)]
+pub struct Name(pub(crate) HyperName);
+
+impl Name {
+    /// View the name as a string.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl FromStr for Name {
+    type Err = BoxError;
+
+    fn from_str(host: &str) -> Result<Self, Self::Err> {
+        HyperName::from_str(host).map(Name).map_err(Into::into)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// Which IP address families a `Client` connects to.
+///
+/// When a host resolves to both IPv4 and IPv6 addresses, the family of the
+/// first address is tried first, and the other one is raced against it
+/// after `ClientBuilder::happy_eyeballs_timeout`. This only applies to
+/// resolved host names; an IP address in a URL is always used as is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressFamily {
+    /// Use the addresses in the order returned by the resolver.
+    Any,
+    /// Try IPv4 addresses first.
+    PreferIpv4,
+    /// Try IPv6 addresses first.
+    PreferIpv6,
+    /// Only connect to IPv4 addresses.
+    Ipv4Only,
+    /// Only connect to IPv6 addresses.
+    Ipv6Only,
+}
+
+impl AddressFamily {
+    fn apply(self, addrs: Addrs) -> Result<Addrs, BoxError> {
+        if self == AddressFamily::Any {
+            return Ok(addrs);
+        }
+
+        let mut addrs = addrs.collect::<Vec<_>>();
+        match self {
+            AddressFamily::Any => (),
+            AddressFamily::PreferIpv4 => addrs.sort_by_key(IpAddr::is_ipv6),
+            AddressFamily::PreferIpv6 => addrs.sort_by_key(IpAddr::is_ipv4),
+            AddressFamily::Ipv4Only | AddressFamily::Ipv6Only => {
+                let ipv4 = self == AddressFamily::Ipv4Only;
+                addrs.retain(|addr| addr.is_ipv4() == ipv4);
+                if addrs.is_empty() {
+                    let family = if ipv4 { "IPv4" } else { "IPv6" };
+                    return Err(format!("no {} addresses found", family).into());
+                }
+            }
+        }
+        Ok(Box::new(addrs.into_iter()))
+    }
+}
+
+/// Adapts a `Resolve` trait object to hyper's resolver interface.
+#[derive(Clone)]
+pub(crate) struct DynResolver {
+    resolver: Arc<dyn Resolve>,
+    family: AddressFamily,
+}
+
+impl DynResolver {
+    pub(crate) fn new(resolver: Arc<dyn Resolve>, family: AddressFamily) -> Self {
+        DynResolver { resolver, family }
+    }
+
+    pub(crate) fn resolve(&self, name: Name) -> Resolving {
+        let resolving = self.resolver.resolve(name);
+        let family = self.family;
+        Box::pin(async move {
+            ConnectTimings::dns(resolving)
+                .await
+                .and_then(|addrs| family.apply(addrs))
+                .map_err(|e| Box::new(crate::error::ResolveFailed(e)) as BoxError)
+        })
+    }
+}
+
+impl Service<HyperName> for DynResolver {
+    type Response = Addrs;
+    type Error = BoxError;
+    type Future = Resolving;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: HyperName) -> Self::Future {
+        self.resolve(Name(name))
+    }
+}
+
+/// Answers lookups for some names from a fixed set of addresses, deferring
+/// every other name to the wrapped resolver.
+pub(crate) struct DnsResolverWithOverrides {
+    dns_resolver: Arc<dyn Resolve>,
+    overrides: Arc<HashMap<String, Vec<SocketAddr>>>,
+}
+
+impl DnsResolverWithOverrides {
+    pub(crate) fn new(
+        dns_resolver: Arc<dyn Resolve>,
+        overrides: HashMap<String, Vec<SocketAddr>>,
+    ) -> Self {
+        DnsResolverWithOverrides {
+            dns_resolver,
+            overrides: Arc::new(overrides),
+        }
+    }
+}
+
+impl Resolve for DnsResolverWithOverrides {
+    fn resolve(&self, name: Name) -> Resolving {
+        match self.overrides.get(name.as_str()) {
+            Some(dest) => {
+                let addrs: Addrs = Box::new(
+                    dest.iter()
+                        .map(SocketAddr::ip)
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                );
+                Box::pin(futures_util::future::ready(Ok(addrs)))
+            }
+            None => self.dns_resolver.resolve(name),
+        }
+    }
+}
+
+impl fmt::Debug for DynResolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("DynResolver")
+    }
+}
@@ -0,0 +1,61 @@
+use std::io;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
+use trust_dns_resolver::{system_conf, TokioAsyncResolver};
+
+use super::{Addrs, Name, Resolve, Resolving};
+
+/// A resolver using trust-dns, configured from the system's `resolv.conf`
+/// (or the registry on Windows).
+///
+/// All lookups go through one shared resolver, so answers are cached and
+/// reused until their TTL expires.
+pub(crate) struct TrustDnsResolver {
+    state: Arc<Mutex<State>>,
+}
+
+enum State {
+    Init(ResolverConfig, ResolverOpts),
+    Ready(TokioAsyncResolver),
+}
+
+impl TrustDnsResolver {
+    pub(crate) fn new() -> io::Result<Self> {
+        let (config, opts) = system_conf::read_system_conf()
+            .map_err(io::Error::from)
+            .map_err(|e| {
+                io::Error::new(e.kind(), format!("error reading DNS system conf: {}", e))
+            })?;
+
+        Ok(TrustDnsResolver {
+            state: Arc::new(Mutex::new(State::Init(config, opts))),
+        })
+    }
+}
+
+impl Resolve for TrustDnsResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let state = self.state.clone();
+        Box::pin(async move {
+            let mut lock = state.lock().await;
+            let resolver = match &*lock {
+                // The resolver spawns its background tasks onto the current
+                // runtime, but a `reqwest::Client` may be constructed before a
+                // runtime is ready. So, it cannot be created *until* the
+                // first lookup is polled.
+                State::Init(config, opts) => {
+                    let resolver = TokioAsyncResolver::tokio(config.clone(), opts.clone()).await?;
+                    *lock = State::Ready(resolver.clone());
+                    resolver
+                }
+                State::Ready(resolver) => resolver.clone(),
+            };
+            drop(lock);
+
+            let lookup = resolver.lookup_ip(name.as_str()).await?;
+            Ok(Box::new(lookup.into_iter()) as Addrs)
+        })
+    }
+}
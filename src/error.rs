@@ -80,6 +80,23 @@ impl Error {
         }
     }
 
+    /// Returns true if the error came from resolving a host name.
+    pub fn is_resolve(&self) -> bool {
+        match self.inner.kind {
+            Kind::Resolve => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the error came from a server certificate not matching
+    /// the pins set with `ClientBuilder::pin_certificate`.
+    pub fn is_certificate_pin(&self) -> bool {
+        match self.inner.kind {
+            Kind::CertificatePin => true,
+            _ => false,
+        }
+    }
+
     /// Returns true if the error is related to a timeout.
     pub fn is_timeout(&self) -> bool {
         self.source().map(|e| e.is::<TimedOut>()).unwrap_or(false)
@@ -140,6 +157,8 @@ impl fmt::Display for Error {
         match self.inner.kind {
             Kind::Builder => f.write_str("builder error")?,
             Kind::Request => f.write_str("error sending request")?,
+            Kind::Resolve => f.write_str("error resolving host name")?,
+            Kind::CertificatePin => f.write_str("error verifying certificate pin")?,
             Kind::Body => f.write_str("request or response body error")?,
             Kind::Decode => f.write_str("error decoding response body")?,
             Kind::Redirect => f.write_str("error following redirect")?,
@@ -188,6 +207,9 @@ impl From<crate::error::Error> for js_sys::Error {
 pub(crate) enum Kind {
     Builder,
     Request,
+    Resolve,
+    #[cfg_attr(not(feature = "__tls"), allow(unused))]
+    CertificatePin,
     Redirect,
     Status(StatusCode),
     Body,
@@ -212,6 +234,27 @@ pub(crate) fn request<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::Request, Some(e))
 }
 
+if_hyper! {
+    /// Converts an error from the hyper `Client`, telling resolver and
+    /// certificate pin failures apart from other errors sending the request.
+    pub(crate) fn from_hyper(e: hyper::Error) -> Error {
+        let mut source = e.source();
+        while let Some(err) = source {
+            if err.is::<ResolveFailed>() {
+                return Error::new(Kind::Resolve, Some(e));
+            }
+            #[cfg(feature = "__tls")]
+            {
+                if err.is::<crate::tls::PinMismatch>() {
+                    return Error::new(Kind::CertificatePin, Some(e));
+                }
+            }
+            source = err.source();
+        }
+        request(e)
+    }
+}
+
 pub(crate) fn redirect<E: Into<BoxError>>(e: E, url: Url) -> Error {
     Error::new(Kind::Redirect, Some(e)).with_url(url)
 }
@@ -262,6 +305,23 @@ impl fmt::Display for TimedOut {
 
 impl StdError for TimedOut {}
 
+/// Marks an error returned by a DNS resolver, so it can be found in the
+/// source chain of the connect error hyper wraps it in.
+#[derive(Debug)]
+pub(crate) struct ResolveFailed(pub(crate) BoxError);
+
+impl fmt::Display for ResolveFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl StdError for ResolveFailed {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.source()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
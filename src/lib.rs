@@ -123,6 +123,19 @@
 //! maximum redirect chain of 10 hops. To customize this behavior, a
 //! [`redirect::Policy`][redirect] can be used with a `ClientBuilder`.
 //!
+//! ## Retries
+//!
+//! By default, a `Client` does not retry failed requests. A
+//! [`retry::Policy`][retry] can be used with a `ClientBuilder`, or for a
+//! single request, to retry idempotent requests that failed to connect or
+//! got a temporary error status, with exponential backoff.
+//!
+//! ## Middleware
+//!
+//! A [`Middleware`][middleware] added to a `ClientBuilder` sees every request
+//! before it is sent, including redirects, and every response before it is
+//! returned. It can modify them, or answer a request without sending it.
+//!
 //! ## Cookies
 //!
 //! The automatic storing and sending of session cookies can be enabled with
@@ -137,6 +150,9 @@
 //! `HTTP_PROXY` or `http_proxy` provide http proxies for http connections while
 //! `HTTPS_PROXY` or `https_proxy` provide HTTPS proxies for HTTPS connections.
 //!
+//! `NO_PROXY` or `no_proxy` provide a comma separated list of hosts, domains,
+//! IP addresses and CIDR blocks that should be connected to directly instead.
+//!
 //! These can be overwritten by adding a [`Proxy`](Proxy) to `ClientBuilder`
 //! i.e. `let proxy = reqwest::Proxy::http("https://secure.example")?;`
 //! or disabled by calling `ClientBuilder::no_proxy()`.
@@ -164,11 +180,23 @@
 //! - **native-tls**: Enables TLS functionality provided by `native-tls`.
 //! - **native-tls-vendored**: Enables the `vendored` feature of `native-tls`.
 //! - **rustls-tls**: Enables TLS functionality provided by `rustls`.
+//!   Equivalent to `rustls-tls-webpki-roots`.
+//! - **rustls-tls-webpki-roots**: Enables TLS functionality provided by
+//!   `rustls`, trusting the root certificates bundled in `webpki-roots`.
+//! - **rustls-tls-native-roots**: Enables TLS functionality provided by
+//!   `rustls`, trusting the root certificates of the platform's native
+//!   store. It can be combined with `rustls-tls-webpki-roots`.
 //! - **blocking**: Provides the [blocking][] client API.
 //! - **cookies**: Provides cookie session support.
-//! - **gzip**: Provides response body gzip decompression.
+//! - **gzip**: Provides response body gzip decompression and request body compression.
+//! - **brotli**: Provides response body brotli decompression and request body compression.
+//! - **deflate**: Provides response body deflate decompression and request body compression.
+//! - **zstd**: Provides response body zstd decompression and request body compression.
 //! - **json**: Provides serialization and deserialization for JSON bodies.
 //! - **stream**: Adds support for `futures::Stream`.
+//! - **socks**: Provides SOCKS5 proxy support.
+//! - **trust-dns**: Enables a trust-dns async resolver instead of default
+//!   threadpool using `getaddrinfo`.
 //!
 //!
 //! [hyper]: http://hyper.rs
@@ -178,13 +206,11 @@
 //! [builder]: ./struct.RequestBuilder.html
 //! [serde]: http://serde.rs
 //! [redirect]: crate::redirect
+//! [retry]: crate::retry
+//! [middleware]: crate::middleware
 //! [Proxy]: ./struct.Proxy.html
 //! [cargo-features]: https://doc.rust-lang.org/stable/cargo/reference/manifest.html#the-features-section
 
-////! - **socks**: Provides SOCKS5 proxy support.
-////! - **trust-dns**: Enables a trust-dns async resolver instead of default
-////!   threadpool using `getaddrinfo`.
-
 macro_rules! if_wasm {
     ($($item:item)*) => {$(
         #[cfg(target_arch = "wasm32")]
@@ -278,9 +304,19 @@ if_hyper! {
     pub use self::async_impl::{
         multipart, Body, Client, ClientBuilder, Request, RequestBuilder, Response, ResponseBuilderExt,
     };
-    pub use self::proxy::Proxy;
+    #[cfg(any(
+        feature = "brotli",
+        feature = "gzip",
+        feature = "deflate",
+        feature = "zstd"
+    ))]
+    pub use self::async_impl::Encoding;
+    pub use self::proxy::{NoProxy, Proxy};
+    pub use self::proxy_auth::ProxyChallenge;
+    pub use self::stats::Stats;
+    pub use self::timings::Timings;
     #[cfg(feature = "__tls")]
-    pub use self::tls::{Certificate, Identity};
+    pub use self::tls::{Certificate, Identity, TlsVersion};
 
 
     mod async_impl;
@@ -289,10 +325,14 @@ if_hyper! {
     mod connect;
     #[cfg(feature = "cookies")]
     pub mod cookie;
-    //#[cfg(feature = "trust-dns")]
-    //mod dns;
+    pub mod dns;
+    pub mod middleware;
     mod proxy;
+    mod proxy_auth;
     pub mod redirect;
+    pub mod retry;
+    mod stats;
+    mod timings;
     #[cfg(feature = "__tls")]
     mod tls;
 }
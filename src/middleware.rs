@@ -0,0 +1,80 @@
+//! Middleware
+//!
+//! A `Middleware` registered with `ClientBuilder::middleware` is called with
+//! every `Request` a `Client` is about to send, and every `Response` it
+//! returns. It can be used to sign requests, add tracing headers, or record
+//! metrics, without wrapping each call site.
+//!
+//! Middleware are called in the order they were registered for requests,
+//! and in the reverse order for responses.
+
+use std::sync::Arc;
+
+use crate::{Request, Response};
+
+/// Intercepts the requests sent and the responses received by a `Client`.
+///
+/// Both methods do nothing by default.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// use reqwest::header::HeaderValue;
+/// use reqwest::middleware::Middleware;
+///
+/// struct TraceId;
+///
+/// impl Middleware for TraceId {
+///     fn on_request(&self, req: &mut reqwest::Request) -> Option<reqwest::Response> {
+///         req.headers_mut()
+///             .insert("x-trace-id", HeaderValue::from_static("42"));
+///         None
+///     }
+/// }
+///
+/// # fn run() -> Result<(), reqwest::Error> {
+/// let client = reqwest::Client::builder()
+///     .middleware(Arc::new(TraceId))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Middleware: Send + Sync {
+    /// Called before a request is sent.
+    ///
+    /// This is called again before following each redirect, with the
+    /// request to the new location.
+    ///
+    /// Returning a `Response` skips sending the request, and that response
+    /// is returned instead. Middleware registered after this one are not
+    /// called.
+    fn on_request(&self, _req: &mut Request) -> Option<Response> {
+        None
+    }
+
+    /// Called with the response that is about to be returned, after all
+    /// redirects have been followed.
+    fn on_response(&self, _res: &mut Response) {}
+}
+
+/// Calls `on_request` on each middleware, until one returns a response.
+///
+/// A returned response has already been passed to the `on_response` of
+/// the middleware before the one that returned it.
+pub(crate) fn on_request(chain: &[Arc<dyn Middleware>], req: &mut Request) -> Option<Response> {
+    for (i, middleware) in chain.iter().enumerate() {
+        if let Some(mut res) = middleware.on_request(req) {
+            on_response(&chain[..i], &mut res);
+            return Some(res);
+        }
+    }
+    None
+}
+
+/// Calls `on_response` on each middleware, in reverse order.
+pub(crate) fn on_response(chain: &[Arc<dyn Middleware>], res: &mut Response) {
+    for middleware in chain.iter().rev() {
+        middleware.on_response(res);
+    }
+}
@@ -0,0 +1,366 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use http::header::{HeaderMap, HeaderValue, PROXY_AUTHENTICATE};
+use http::Method;
+
+use crate::proxy::encode_basic_auth;
+
+/// An authentication challenge from a proxy, sent in a
+/// `407 Proxy Authentication Required` response.
+///
+/// See `Proxy::auth_handler`.
+#[derive(Clone, Debug)]
+pub struct ProxyChallenge {
+    method: Method,
+    uri: String,
+    proxy_authenticate: Vec<HeaderValue>,
+}
+
+impl ProxyChallenge {
+    /// The method of the request to authenticate, `CONNECT` for a tunnel.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The target of the request to authenticate, as sent to the proxy.
+    ///
+    /// This is `host:port` for a tunnel, and the absolute URL otherwise.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// The `Proxy-Authenticate` headers of the response.
+    pub fn proxy_authenticate(&self) -> &[HeaderValue] {
+        &self.proxy_authenticate
+    }
+
+    pub(crate) fn new(method: Method, uri: String, headers: &HeaderMap) -> ProxyChallenge {
+        ProxyChallenge {
+            method,
+            uri,
+            proxy_authenticate: headers
+                .get_all(PROXY_AUTHENTICATE)
+                .iter()
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+type Handler = dyn Fn(&ProxyChallenge) -> Option<HeaderValue> + Send + Sync + 'static;
+
+/// Answers the challenges of a proxy with a `Proxy-Authorization` header.
+#[derive(Clone)]
+pub(crate) struct AuthHandler(Arc<Handler>);
+
+impl AuthHandler {
+    pub(crate) fn new<F>(handler: F) -> AuthHandler
+    where
+        F: Fn(&ProxyChallenge) -> Option<HeaderValue> + Send + Sync + 'static,
+    {
+        AuthHandler(Arc::new(handler))
+    }
+
+    pub(crate) fn answer(&self, challenge: &ProxyChallenge) -> Option<HeaderValue> {
+        (self.0)(challenge)
+    }
+}
+
+impl fmt::Debug for AuthHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AuthHandler")
+    }
+}
+
+/// Credentials answering Digest challenges, and Basic ones when the proxy
+/// doesn't offer Digest.
+pub(crate) struct Credentials {
+    username: String,
+    password: String,
+}
+
+impl Credentials {
+    pub(crate) fn new(username: &str, password: &str) -> Credentials {
+        Credentials {
+            username: username.to_owned(),
+            password: password.to_owned(),
+        }
+    }
+
+    pub(crate) fn answer(&self, challenge: &ProxyChallenge) -> Option<HeaderValue> {
+        let challenges = challenge
+            .proxy_authenticate()
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(parse_challenges)
+            .collect::<Vec<_>>();
+
+        if let Some(digest) = challenges
+            .iter()
+            .find(|c| c.scheme.eq_ignore_ascii_case("digest"))
+        {
+            return self.digest(challenge, digest, &cnonce());
+        }
+        if challenges
+            .iter()
+            .any(|c| c.scheme.eq_ignore_ascii_case("basic"))
+        {
+            return Some(encode_basic_auth(&self.username, &self.password));
+        }
+        None
+    }
+
+    fn digest(
+        &self,
+        challenge: &ProxyChallenge,
+        digest: &Challenge,
+        cnonce: &str,
+    ) -> Option<HeaderValue> {
+        let realm = digest.param("realm")?;
+        let nonce = digest.param("nonce")?;
+        let algorithm = digest.param("algorithm").unwrap_or("MD5");
+        let qop = match digest.param("qop") {
+            Some(qop) => {
+                // Only `auth` is supported, `auth-int` needs the body.
+                qop.split(',').find(|qop| qop.trim() == "auth")?;
+                Some("auth")
+            }
+            None => None,
+        };
+
+        let mut ha1 = md5(&format!("{}:{}:{}", self.username, realm, self.password));
+        if algorithm.eq_ignore_ascii_case("MD5-sess") {
+            ha1 = md5(&format!("{}:{}:{}", ha1, nonce, cnonce));
+        } else if !algorithm.eq_ignore_ascii_case("MD5") {
+            return None;
+        }
+        let ha2 = md5(&format!("{}:{}", challenge.method(), challenge.uri()));
+        let nc = "00000001";
+        let response = match qop {
+            Some(qop) => md5(&format!(
+                "{}:{}:{}:{}:{}:{}",
+                ha1, nonce, nc, cnonce, qop, ha2
+            )),
+            None => md5(&format!("{}:{}:{}", ha1, nonce, ha2)),
+        };
+
+        let mut header = format!(
+            "Digest username={}, realm={}, nonce={}, uri={}, algorithm={}, response=\"{}\"",
+            quote(&self.username),
+            quote(realm),
+            quote(nonce),
+            quote(challenge.uri()),
+            algorithm,
+            response,
+        );
+        if let Some(opaque) = digest.param("opaque") {
+            header.push_str(&format!(", opaque={}", quote(opaque)));
+        }
+        if let Some(qop) = qop {
+            header.push_str(&format!(", qop={}, nc={}, cnonce=\"{}\"", qop, nc, cnonce));
+        }
+
+        let mut header = HeaderValue::from_str(&header).ok()?;
+        header.set_sensitive(true);
+        Some(header)
+    }
+}
+
+/// A challenge of a `Proxy-Authenticate` header, such as
+/// `Digest realm="proxy", nonce="abc"`.
+#[derive(Debug, PartialEq)]
+struct Challenge {
+    scheme: String,
+    params: Vec<(String, String)>,
+}
+
+impl Challenge {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parses the comma separated challenges of a `Proxy-Authenticate` header.
+///
+/// A `token68` credential after a scheme is skipped.
+fn parse_challenges(value: &str) -> Vec<Challenge> {
+    let mut challenges: Vec<Challenge> = Vec::new();
+    let mut after_scheme = false;
+    let mut rest = value.trim_start();
+    while !rest.is_empty() {
+        if rest.starts_with(',') {
+            after_scheme = false;
+            rest = rest[1..].trim_start();
+            continue;
+        }
+
+        let (token, after) = split_token(rest);
+        if token.is_empty() {
+            break;
+        }
+        let after = after.trim_start();
+        let padding = after.trim_start_matches('=');
+        if after_scheme
+            && (!after.starts_with('=') || padding.is_empty() || padding.starts_with(','))
+        {
+            // A token68 credential, such as `Negotiate abc==`.
+            after_scheme = false;
+            rest = padding.trim_start();
+        } else if let Some(value) = after.strip_prefix('=') {
+            let (value, after) = split_value(value.trim_start());
+            if let Some(challenge) = challenges.last_mut() {
+                challenge.params.push((token.to_owned(), value));
+            }
+            after_scheme = false;
+            rest = after.trim_start();
+        } else {
+            challenges.push(Challenge {
+                scheme: token.to_owned(),
+                params: Vec::new(),
+            });
+            after_scheme = true;
+            rest = after;
+        }
+    }
+    challenges
+}
+
+fn split_token(s: &str) -> (&str, &str) {
+    let end = s
+        .find(|c: char| !(c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)))
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn split_value(s: &str) -> (String, &str) {
+    if !s.starts_with('"') {
+        let (token, rest) = split_token(s);
+        return (token.to_owned(), rest);
+    }
+
+    let mut value = String::new();
+    let mut chars = s[1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (value, &s[i + 2..]),
+            '\\' => {
+                if let Some((_, c)) = chars.next() {
+                    value.push(c);
+                }
+            }
+            c => value.push(c),
+        }
+    }
+    // An unterminated quoted string takes the rest of the header.
+    (value, "")
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn md5(s: &str) -> String {
+    format!("{:x}", md5::compute(s))
+}
+
+/// A client nonce, unique for this process.
+fn cnonce() -> String {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let count = COUNT.fetch_add(1, Ordering::Relaxed);
+    md5(&format!(
+        "{}:{}:{}",
+        now.as_nanos(),
+        std::process::id(),
+        count
+    ))[..16]
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge(method: Method, uri: &str, proxy_authenticate: &str) -> ProxyChallenge {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            PROXY_AUTHENTICATE,
+            HeaderValue::from_str(proxy_authenticate).unwrap(),
+        );
+        ProxyChallenge::new(method, uri.to_owned(), &headers)
+    }
+
+    #[test]
+    fn parse_challenges_with_params() {
+        let challenges = parse_challenges(
+            r#"Negotiate abc, Digest realm="a \"b\", c", qop="auth,auth-int", stale=true, Basic realm=x"#,
+        );
+
+        assert_eq!(challenges.len(), 3);
+        assert_eq!(challenges[0].scheme, "Negotiate");
+        assert_eq!(challenges[1].scheme, "Digest");
+        assert_eq!(challenges[1].param("realm"), Some("a \"b\", c"));
+        assert_eq!(challenges[1].param("QOP"), Some("auth,auth-int"));
+        assert_eq!(challenges[1].param("stale"), Some("true"));
+        assert_eq!(challenges[2].scheme, "Basic");
+        assert_eq!(challenges[2].param("realm"), Some("x"));
+    }
+
+    #[test]
+    fn digest_rfc2617_example() {
+        let challenge = challenge(
+            Method::GET,
+            "/dir/index.html",
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        );
+        let credentials = Credentials::new("Mufasa", "Circle Of Life");
+        let digest = &parse_challenges(challenge.proxy_authenticate()[0].to_str().unwrap())[0];
+
+        let header = credentials.digest(&challenge, digest, "0a4f113b").unwrap();
+
+        assert_eq!(
+            header,
+            "Digest username=\"Mufasa\", realm=\"testrealm@host.com\", \
+             nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", uri=\"/dir/index.html\", \
+             algorithm=MD5, response=\"6629fae49393a05397450978507c4ef1\", \
+             opaque=\"5ccc069c403ebaf9f0171e9517f40e41\", qop=auth, nc=00000001, \
+             cnonce=\"0a4f113b\""
+        );
+        assert!(header.is_sensitive());
+    }
+
+    #[test]
+    fn basic_when_digest_not_offered() {
+        let challenge = challenge(Method::CONNECT, "hyper.rs:443", r#"Basic realm="proxy""#);
+        let credentials = Credentials::new("Aladdin", "open sesame");
+
+        assert_eq!(
+            credentials.answer(&challenge).unwrap(),
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+    }
+
+    #[test]
+    fn unsupported_challenges() {
+        let credentials = Credentials::new("Aladdin", "open sesame");
+
+        let ntlm = challenge(Method::GET, "http://hyper.rs/", "NTLM");
+        assert_eq!(credentials.answer(&ntlm), None);
+
+        let sha = challenge(
+            Method::GET,
+            "http://hyper.rs/",
+            r#"Digest realm="r", nonce="n", algorithm=SHA-256"#,
+        );
+        assert_eq!(credentials.answer(&sha), None);
+    }
+}
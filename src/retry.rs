@@ -0,0 +1,352 @@
+//! Retry Handling
+//!
+//! By default, a `Client` does not retry failed requests. To re-send
+//! requests that failed to connect or got a temporary error response, a
+//! `retry::Policy` can be used with a `ClientBuilder`, or for a single
+//! request with `RequestBuilder::retry`.
+//!
+//! Only requests with an idempotent method (`GET`, `HEAD`, `OPTIONS`,
+//! `TRACE`, `PUT` and `DELETE`) are retried, and only if their body can be
+//! sent again. Bodies created from a stream cannot, and are never retried.
+
+use std::collections::hash_map::RandomState;
+use std::error::Error as StdError;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::time::Duration;
+
+use hyper::StatusCode;
+
+use crate::header::{HeaderMap, RETRY_AFTER};
+use crate::Method;
+
+/// A type that controls the policy on how to retry failed requests.
+///
+/// The default value never retries.
+///
+/// - `limited` creates a policy that retries up to a maximum number of times,
+///   on connection errors and on the statuses `429`, `502`, `503` and `504`.
+/// - `none` can be used to disable retries, for instance to override a
+///   client's policy for a single request.
+///
+/// Between attempts, the policy waits with an exponential backoff, doubling
+/// the delay after every retry. When the response has a `Retry-After`
+/// header, that delay is used instead.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use reqwest::{retry, StatusCode};
+/// #
+/// # fn run() -> Result<(), reqwest::Error> {
+/// let policy = retry::Policy::limited(3)
+///     .statuses(&[StatusCode::SERVICE_UNAVAILABLE])
+///     .backoff(Duration::from_millis(50), Duration::from_secs(2));
+///
+/// let client = reqwest::Client::builder()
+///     .retry(policy)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Policy {
+    max_retries: usize,
+    statuses: Vec<StatusCode>,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    retry_after: bool,
+}
+
+impl Policy {
+    /// Create a `Policy` that retries a request up to `max_retries` times.
+    pub fn limited(max_retries: usize) -> Self {
+        Self {
+            max_retries,
+            statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+            retry_after: true,
+        }
+    }
+
+    /// Create a `Policy` that does not retry any request.
+    pub fn none() -> Self {
+        Self::limited(0)
+    }
+
+    /// Set the response statuses that cause a request to be retried.
+    ///
+    /// Requests failing to connect are always retried.
+    pub fn statuses(mut self, statuses: &[StatusCode]) -> Self {
+        self.statuses = statuses.to_vec();
+        self
+    }
+
+    /// Set the delay before the first retry, and the maximum delay between
+    /// any two attempts.
+    ///
+    /// Default is 100 milliseconds, doubling up to 10 seconds.
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_delay = base;
+        self.max_delay = max;
+        self
+    }
+
+    /// Enable or disable randomizing the backoff delays.
+    ///
+    /// With jitter, each delay is picked at random between half and the
+    /// whole of the exponential backoff, so that many clients failing at
+    /// once don't all retry at the same instant.
+    ///
+    /// Default is enabled.
+    pub fn jitter(mut self, enable: bool) -> Self {
+        self.jitter = enable;
+        self
+    }
+
+    /// Enable or disable honoring the `Retry-After` response header.
+    ///
+    /// When enabled, a `Retry-After` delay takes the place of the backoff.
+    /// If it asks to wait longer than the maximum backoff delay, the
+    /// response is returned instead of retrying.
+    ///
+    /// Default is enabled.
+    pub fn retry_after(mut self, enable: bool) -> Self {
+        self.retry_after = enable;
+        self
+    }
+
+    /// Returns how long to wait before retrying a request that failed
+    /// without a response, or `None` if it should not be retried.
+    pub(crate) fn retry_error(
+        &self,
+        method: &Method,
+        retries: usize,
+        err: &hyper::Error,
+    ) -> Option<Duration> {
+        if !self.can_retry(method, retries) || !is_retryable_error(err) {
+            return None;
+        }
+        Some(self.backoff_delay(retries))
+    }
+
+    /// Returns how long to wait before retrying a request that got this
+    /// response, or `None` if the response should be returned.
+    pub(crate) fn retry_response(
+        &self,
+        method: &Method,
+        retries: usize,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Option<Duration> {
+        if !self.can_retry(method, retries) || !self.statuses.contains(&status) {
+            return None;
+        }
+
+        if self.retry_after {
+            if let Some(delay) = headers.get(RETRY_AFTER).and_then(parse_retry_after) {
+                if delay > self.max_delay {
+                    return None;
+                }
+                return Some(delay);
+            }
+        }
+        Some(self.backoff_delay(retries))
+    }
+
+    pub(crate) fn is_default(&self) -> bool {
+        self.max_retries == 0
+    }
+
+    fn can_retry(&self, method: &Method, retries: usize) -> bool {
+        retries < self.max_retries && is_idempotent(method)
+    }
+
+    fn backoff_delay(&self, retries: usize) -> Duration {
+        let delay = 2u32
+            .checked_pow(retries as u32)
+            .and_then(|factor| self.base_delay.checked_mul(factor))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        if self.jitter {
+            let half = delay / 2;
+            half + random_up_to(delay - half)
+        } else {
+            delay
+        }
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Policy {
+        // Keep `is_default` in sync
+        Policy::none()
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    match *method {
+        Method::GET
+        | Method::HEAD
+        | Method::OPTIONS
+        | Method::TRACE
+        | Method::PUT
+        | Method::DELETE => true,
+        _ => false,
+    }
+}
+
+/// Errors where the server never got, or never answered, the request: the
+/// connection could not be established, or a pooled connection was closed
+/// or reset from under it.
+fn is_retryable_error(err: &hyper::Error) -> bool {
+    if err.is_connect() || err.is_incomplete_message() {
+        return true;
+    }
+
+    let mut source = err.source();
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<io::Error>() {
+            match io.kind() {
+                io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe => return true,
+                _ => (),
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Parses a `Retry-After` value, either as a number of seconds or as an
+/// HTTP-date.
+fn parse_retry_after(value: &crate::header::HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = time::strptime(value, "%a, %d %b %Y %T GMT").ok()?;
+    let wait = date.to_timespec() - time::now_utc().to_timespec();
+    // A date in the past means "retry now".
+    Some(wait.to_std().unwrap_or_else(|_| Duration::from_secs(0)))
+}
+
+fn random_up_to(max: Duration) -> Duration {
+    let nanos = max.as_nanos() as u64;
+    if nanos == 0 {
+        return max;
+    }
+    // Every `RandomState` is seeded differently, which is enough randomness
+    // to spread out retries.
+    let rand = RandomState::new().build_hasher().finish();
+    Duration::from_nanos(rand % (nanos + 1))
+}
+
+#[test]
+fn test_retry_policy_none() {
+    let policy = Policy::default();
+    assert!(policy.is_default());
+    assert_eq!(
+        policy.retry_response(
+            &Method::GET,
+            0,
+            StatusCode::SERVICE_UNAVAILABLE,
+            &HeaderMap::new()
+        ),
+        None
+    );
+}
+
+#[test]
+fn test_retry_policy_limited() {
+    let policy = Policy::limited(2).jitter(false);
+    let headers = HeaderMap::new();
+
+    assert_eq!(
+        policy.retry_response(&Method::GET, 0, StatusCode::SERVICE_UNAVAILABLE, &headers),
+        Some(Duration::from_millis(100))
+    );
+    assert_eq!(
+        policy.retry_response(&Method::GET, 1, StatusCode::SERVICE_UNAVAILABLE, &headers),
+        Some(Duration::from_millis(200))
+    );
+    assert_eq!(
+        policy.retry_response(&Method::GET, 2, StatusCode::SERVICE_UNAVAILABLE, &headers),
+        None
+    );
+    assert_eq!(
+        policy.retry_response(&Method::GET, 0, StatusCode::INTERNAL_SERVER_ERROR, &headers),
+        None
+    );
+    assert_eq!(
+        policy.retry_response(&Method::POST, 0, StatusCode::SERVICE_UNAVAILABLE, &headers),
+        None
+    );
+}
+
+#[test]
+fn test_retry_policy_backoff() {
+    let policy = Policy::limited(10)
+        .backoff(Duration::from_secs(1), Duration::from_secs(5))
+        .jitter(false);
+
+    let delays = (0..5)
+        .map(|retries| policy.backoff_delay(retries).as_secs())
+        .collect::<Vec<_>>();
+    assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+
+    let policy = policy.jitter(true);
+    for retries in 0..5 {
+        let delay = policy.backoff_delay(retries);
+        let full = Duration::from_secs(delays[retries]);
+        assert!(delay >= full / 2 && delay <= full, "{:?}", delay);
+    }
+}
+
+#[test]
+fn test_retry_policy_retry_after() {
+    use crate::header::HeaderValue;
+
+    let policy = Policy::limited(1).backoff(Duration::from_millis(100), Duration::from_secs(5));
+    let mut headers = HeaderMap::new();
+
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+    assert_eq!(
+        policy.retry_response(&Method::GET, 0, StatusCode::SERVICE_UNAVAILABLE, &headers),
+        Some(Duration::from_secs(3))
+    );
+
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("60"));
+    assert_eq!(
+        policy.retry_response(&Method::GET, 0, StatusCode::SERVICE_UNAVAILABLE, &headers),
+        None
+    );
+
+    headers.insert(
+        RETRY_AFTER,
+        HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+    );
+    assert_eq!(
+        policy.retry_response(&Method::GET, 0, StatusCode::SERVICE_UNAVAILABLE, &headers),
+        Some(Duration::from_secs(0))
+    );
+
+    let policy = policy.retry_after(false).jitter(false);
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("60"));
+    assert_eq!(
+        policy.retry_response(&Method::GET, 0, StatusCode::SERVICE_UNAVAILABLE, &headers),
+        Some(Duration::from_millis(100))
+    );
+}
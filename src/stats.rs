@@ -0,0 +1,146 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::timings::ConnectTimings;
+
+/// A snapshot of the statistics of a `Client`.
+///
+/// See `Client::stats`. The counters start at zero when the `Client` is
+/// built, and are shared by its clones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    connections_created: u64,
+    connections_reused: u64,
+    requests_in_flight: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
+    redirects_followed: u64,
+}
+
+impl Stats {
+    /// The number of connections opened, including connections to proxies.
+    pub fn connections_created(&self) -> u64 {
+        self.connections_created
+    }
+
+    /// The number of responses received over a connection that was already
+    /// used by an earlier response.
+    pub fn connections_reused(&self) -> u64 {
+        self.connections_reused
+    }
+
+    /// The number of requests sent, whose response headers haven't been
+    /// received yet.
+    pub fn requests_in_flight(&self) -> u64 {
+        self.requests_in_flight
+    }
+
+    /// The number of HTTP bytes written to connections.
+    ///
+    /// TLS records and proxy `CONNECT` requests aren't counted.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// The number of HTTP bytes read from connections.
+    ///
+    /// TLS records and proxy `CONNECT` responses aren't counted.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// The number of redirects followed.
+    pub fn redirects_followed(&self) -> u64 {
+        self.redirects_followed
+    }
+}
+
+/// The live counters of a `Client`, shared with its `Connector`.
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    connections_created: AtomicU64,
+    connections_reused: AtomicU64,
+    requests_in_flight: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    redirects_followed: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn connection_created(&self) {
+        self.connections_created.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn connection_reused(&self) {
+        self.connections_reused.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn bytes_sent(&self, n: usize) {
+        self.bytes_sent.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn bytes_received(&self, n: usize) {
+        self.bytes_received.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn redirect_followed(&self) {
+        self.redirects_followed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> Stats {
+        Stats {
+            connections_created: self.connections_created.load(Ordering::Relaxed),
+            connections_reused: self.connections_reused.load(Ordering::Relaxed),
+            requests_in_flight: self.requests_in_flight.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            redirects_followed: self.redirects_followed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counts a request as in flight until dropped.
+#[derive(Debug)]
+pub(crate) struct InFlight(Arc<Counters>);
+
+impl InFlight {
+    pub(crate) fn new(counters: Arc<Counters>) -> InFlight {
+        counters.requests_in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(counters)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.requests_in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// How a connection was opened, and whether it has received a response yet.
+///
+/// The connector sets one in the extensions of every response received over
+/// a connection, through `Connected::extra`.
+#[derive(Clone, Debug)]
+pub(crate) struct ConnInfo {
+    used: Arc<AtomicBool>,
+    timings: ConnectTimings,
+}
+
+impl ConnInfo {
+    pub(crate) fn new(timings: ConnectTimings) -> ConnInfo {
+        ConnInfo {
+            used: Arc::new(AtomicBool::new(false)),
+            timings,
+        }
+    }
+
+    /// Returns the timings of the connection for the first response
+    /// received over it, and `None` for the next ones.
+    pub(crate) fn first_use(&self) -> Option<ConnectTimings> {
+        if self.used.swap(true, Ordering::Relaxed) {
+            None
+        } else {
+            Some(self.timings)
+        }
+    }
+}
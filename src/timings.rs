@@ -0,0 +1,206 @@
+use std::cell::Cell;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// How long the phases of a request took.
+///
+/// See `Response::timings`. The connection phases are only known for a
+/// request that opened a new connection, and are `None` for a request sent
+/// over a pooled connection.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Timings {
+    dns: Option<Duration>,
+    connect: Option<Duration>,
+    tls: Option<Duration>,
+    ttfb: Duration,
+    total: Duration,
+    redirects: Vec<Timings>,
+}
+
+impl Timings {
+    /// The time spent resolving the host of the new connection, or of its
+    /// proxy.
+    ///
+    /// This is `None` when no lookup was needed, such as for an IP address
+    /// or a custom connector.
+    pub fn dns(&self) -> Option<Duration> {
+        self.dns
+    }
+
+    /// The time spent establishing the new connection, after DNS, including
+    /// any proxy tunnel.
+    pub fn connect(&self) -> Option<Duration> {
+        self.connect
+    }
+
+    /// The time spent in TLS handshakes on the new connection.
+    ///
+    /// This is `None` when no handshake was made, such as for an `http` URL
+    /// without an `https` proxy.
+    pub fn tls(&self) -> Option<Duration> {
+        self.tls
+    }
+
+    /// The time from sending the request until the response headers were
+    /// received, without the time spent opening a connection.
+    pub fn ttfb(&self) -> Duration {
+        self.ttfb
+    }
+
+    /// The time from sending the request until the response headers were
+    /// received, including the time spent opening a connection.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// The timings of the redirects followed before this request, in order.
+    pub fn redirects(&self) -> &[Timings] {
+        &self.redirects
+    }
+
+    pub(crate) fn new(total: Duration, conn: Option<ConnectTimings>) -> Timings {
+        let conn = match conn {
+            Some(conn) => conn,
+            None => {
+                return Timings {
+                    ttfb: total,
+                    total,
+                    ..Timings::default()
+                };
+            }
+        };
+
+        let dns = conn.dns.unwrap_or_default();
+        let connect = conn.transport.checked_sub(dns).unwrap_or_default() + conn.tunnel;
+        let tls = if conn.tls {
+            let setup = conn.transport + conn.tunnel;
+            Some(conn.total.checked_sub(setup).unwrap_or_default())
+        } else {
+            None
+        };
+
+        Timings {
+            dns: conn.dns,
+            connect: Some(connect),
+            tls,
+            ttfb: total.checked_sub(conn.total).unwrap_or_default(),
+            total,
+            redirects: Vec::new(),
+        }
+    }
+
+    pub(crate) fn set_redirects(&mut self, redirects: Vec<Timings>) {
+        self.redirects = redirects;
+    }
+}
+
+tokio::task_local! {
+    static CONNECTING: Cell<ConnectTimings>;
+}
+
+/// How long opening a connection took.
+///
+/// The phases are recorded while the `Connector` runs, and the totals are
+/// kept with the connection.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ConnectTimings {
+    dns: Option<Duration>,
+    transport: Duration,
+    tunnel: Duration,
+    total: Duration,
+    tls: bool,
+}
+
+impl ConnectTimings {
+    /// Runs `f`, which opens a connection, recording its phases.
+    ///
+    /// `tls` tells whether the connection makes any TLS handshake; their
+    /// duration is the time not spent in the other phases.
+    pub(crate) async fn collect<F: Future>(f: F, tls: bool) -> (F::Output, ConnectTimings) {
+        CONNECTING
+            .scope(Cell::new(ConnectTimings::default()), async move {
+                let start = Instant::now();
+                let out = f.await;
+                let mut timings = CONNECTING.with(Cell::get);
+                timings.total = start.elapsed();
+                timings.tls = tls;
+                (out, timings)
+            })
+            .await
+    }
+
+    /// Runs `f`, recording it as a DNS lookup.
+    pub(crate) async fn dns<F: Future>(f: F) -> F::Output {
+        record(f, |t, elapsed| {
+            t.dns = Some(t.dns.unwrap_or_default() + elapsed)
+        })
+        .await
+    }
+
+    /// Runs `f`, recording it as opening a transport, including its DNS
+    /// lookups.
+    pub(crate) async fn transport<F: Future>(f: F) -> F::Output {
+        record(f, |t, elapsed| t.transport += elapsed).await
+    }
+
+    /// Runs `f`, recording it as establishing a proxy tunnel.
+    #[cfg(feature = "__tls")]
+    pub(crate) async fn tunnel<F: Future>(f: F) -> F::Output {
+        record(f, |t, elapsed| t.tunnel += elapsed).await
+    }
+}
+
+async fn record<F: Future>(f: F, add: fn(&mut ConnectTimings, Duration)) -> F::Output {
+    let start = Instant::now();
+    let out = f.await;
+    let _ = CONNECTING.try_with(|cell| {
+        let mut timings = cell.get();
+        add(&mut timings, start.elapsed());
+        cell.set(timings);
+    });
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn timings_from_connect_phases() {
+        let conn = ConnectTimings {
+            dns: Some(ms(10)),
+            transport: ms(30),
+            tunnel: ms(5),
+            total: ms(60),
+            tls: true,
+        };
+
+        let timings = Timings::new(ms(100), Some(conn));
+
+        assert_eq!(timings.dns(), Some(ms(10)));
+        assert_eq!(timings.connect(), Some(ms(25)));
+        assert_eq!(timings.tls(), Some(ms(25)));
+        assert_eq!(timings.ttfb(), ms(40));
+        assert_eq!(timings.total(), ms(100));
+    }
+
+    #[test]
+    fn timings_without_tls() {
+        let conn = ConnectTimings {
+            transport: ms(30),
+            total: ms(30),
+            ..ConnectTimings::default()
+        };
+
+        let timings = Timings::new(ms(50), Some(conn));
+
+        assert_eq!(timings.dns(), None);
+        assert_eq!(timings.connect(), Some(ms(30)));
+        assert_eq!(timings.tls(), None);
+        assert_eq!(timings.ttfb(), ms(20));
+    }
+}
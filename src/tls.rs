@@ -1,7 +1,9 @@
-#[cfg(feature = "rustls-tls")]
+#[cfg(feature = "__rustls")]
 use rustls::{RootCertStore, ServerCertVerified, ServerCertVerifier, TLSError};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fmt;
-#[cfg(feature = "rustls-tls")]
+#[cfg(feature = "__rustls")]
 use tokio_rustls::webpki::DNSNameRef;
 
 /// Represents a server X509 certificate.
@@ -9,11 +11,11 @@ use tokio_rustls::webpki::DNSNameRef;
 pub struct Certificate {
     #[cfg(feature = "native-tls-crate")]
     native: native_tls_crate::Certificate,
-    #[cfg(feature = "rustls-tls")]
+    #[cfg(feature = "__rustls")]
     original: Cert,
 }
 
-#[cfg(feature = "rustls-tls")]
+#[cfg(feature = "__rustls")]
 #[derive(Clone)]
 enum Cert {
     Der(Vec<u8>),
@@ -23,7 +25,7 @@ enum Cert {
 /// Represents a private key and X509 cert as a client certificate.
 pub struct Identity {
     #[cfg_attr(
-        not(any(feature = "native-tls", feature = "rustls-tls")),
+        not(any(feature = "native-tls", feature = "__rustls")),
         allow(unused)
     )]
     inner: ClientCert,
@@ -32,7 +34,7 @@ pub struct Identity {
 enum ClientCert {
     #[cfg(feature = "native-tls")]
     Pkcs12(native_tls_crate::Identity),
-    #[cfg(feature = "rustls-tls")]
+    #[cfg(feature = "__rustls")]
     Pem {
         key: rustls::PrivateKey,
         certs: Vec<rustls::Certificate>,
@@ -60,7 +62,7 @@ impl Certificate {
         Ok(Certificate {
             #[cfg(feature = "native-tls-crate")]
             native: native_tls_crate::Certificate::from_der(der).map_err(crate::error::builder)?,
-            #[cfg(feature = "rustls-tls")]
+            #[cfg(feature = "__rustls")]
             original: Cert::Der(der.to_owned()),
         })
     }
@@ -85,7 +87,7 @@ impl Certificate {
         Ok(Certificate {
             #[cfg(feature = "native-tls-crate")]
             native: native_tls_crate::Certificate::from_pem(pem).map_err(crate::error::builder)?,
-            #[cfg(feature = "rustls-tls")]
+            #[cfg(feature = "__rustls")]
             original: Cert::Pem(pem.to_owned()),
         })
     }
@@ -95,7 +97,7 @@ impl Certificate {
         tls.add_root_certificate(self.native);
     }
 
-    #[cfg(feature = "rustls-tls")]
+    #[cfg(feature = "__rustls")]
     pub(crate) fn add_to_rustls(self, tls: &mut rustls::ClientConfig) -> crate::Result<()> {
         use rustls::internal::pemfile;
         use std::io::Cursor;
@@ -187,7 +189,7 @@ impl Identity {
     /// # Optional
     ///
     /// This requires the `rustls-tls` Cargo feature enabled.
-    #[cfg(feature = "rustls-tls")]
+    #[cfg(feature = "__rustls")]
     pub fn from_pem(buf: &[u8]) -> crate::Result<Identity> {
         use rustls::internal::pemfile;
         use std::io::Cursor;
@@ -236,12 +238,12 @@ impl Identity {
                 tls.identity(id);
                 Ok(())
             }
-            #[cfg(feature = "rustls-tls")]
+            #[cfg(feature = "__rustls")]
             ClientCert::Pem { .. } => Err(crate::error::builder("incompatible TLS identity type")),
         }
     }
 
-    #[cfg(feature = "rustls-tls")]
+    #[cfg(feature = "__rustls")]
     pub(crate) fn add_to_rustls(self, tls: &mut rustls::ClientConfig) -> crate::Result<()> {
         match self.inner {
             ClientCert::Pem { key, certs } => {
@@ -266,12 +268,200 @@ impl fmt::Debug for Identity {
     }
 }
 
+/// A version of the TLS protocol.
+///
+/// See `ClientBuilder::min_tls_version` and `ClientBuilder::max_tls_version`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TlsVersion(InnerVersion);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum InnerVersion {
+    Tls1_0,
+    Tls1_1,
+    Tls1_2,
+    Tls1_3,
+}
+
+impl TlsVersion {
+    /// Version 1.0 of the TLS protocol.
+    pub const TLS_1_0: TlsVersion = TlsVersion(InnerVersion::Tls1_0);
+    /// Version 1.1 of the TLS protocol.
+    pub const TLS_1_1: TlsVersion = TlsVersion(InnerVersion::Tls1_1);
+    /// Version 1.2 of the TLS protocol.
+    pub const TLS_1_2: TlsVersion = TlsVersion(InnerVersion::Tls1_2);
+    /// Version 1.3 of the TLS protocol.
+    pub const TLS_1_3: TlsVersion = TlsVersion(InnerVersion::Tls1_3);
+
+    /// The native-tls protocol for this version, which has none for TLS 1.3.
+    #[cfg(feature = "default-tls")]
+    pub(crate) fn to_native_tls(self) -> Option<native_tls_crate::Protocol> {
+        match self.0 {
+            InnerVersion::Tls1_0 => Some(native_tls_crate::Protocol::Tlsv10),
+            InnerVersion::Tls1_1 => Some(native_tls_crate::Protocol::Tlsv11),
+            InnerVersion::Tls1_2 => Some(native_tls_crate::Protocol::Tlsv12),
+            InnerVersion::Tls1_3 => None,
+        }
+    }
+
+    #[cfg(feature = "__rustls")]
+    pub(crate) fn from_rustls(version: rustls::ProtocolVersion) -> Option<TlsVersion> {
+        match version {
+            rustls::ProtocolVersion::TLSv1_0 => Some(TlsVersion::TLS_1_0),
+            rustls::ProtocolVersion::TLSv1_1 => Some(TlsVersion::TLS_1_1),
+            rustls::ProtocolVersion::TLSv1_2 => Some(TlsVersion::TLS_1_2),
+            rustls::ProtocolVersion::TLSv1_3 => Some(TlsVersion::TLS_1_3),
+            _ => None,
+        }
+    }
+}
+
+/// Looks up the cipher suite supported by rustls named `name`, as
+/// registered with IANA, such as `TLS_AES_128_GCM_SHA256`.
+#[cfg(feature = "__rustls")]
+pub(crate) fn rustls_cipher_suite(name: &str) -> Option<&'static rustls::SupportedCipherSuite> {
+    rustls::ALL_CIPHERSUITES.iter().copied().find(|suite| {
+        // rustls names the TLS 1.3 suites `TLS13_*`.
+        format!("{:?}", suite.suite).replacen("TLS13_", "TLS_", 1) == name
+    })
+}
+
+/// SHA-256 hashes of the public keys accepted for some hosts, set with
+/// `ClientBuilder::pin_certificate`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CertificatePins {
+    pins: HashMap<String, Vec<[u8; 32]>>,
+}
+
+impl CertificatePins {
+    pub(crate) fn add(&mut self, host: &str, sha256_spki: [u8; 32]) {
+        self.pins
+            .entry(normalize_host(host))
+            .or_default()
+            .push(sha256_spki);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pins.is_empty()
+    }
+
+    /// Checks the DER certificate a server presented for `host` against
+    /// the pins of `host`, if any.
+    pub(crate) fn verify(&self, host: &str, cert: Option<&[u8]>) -> Result<(), PinMismatch> {
+        let pins = match self.pins.get(&normalize_host(host)) {
+            Some(pins) => pins,
+            None => return Ok(()),
+        };
+        if let Some(spki) = cert.and_then(subject_public_key_info) {
+            let hash = Sha256::digest(spki);
+            if pins.iter().any(|pin| pin[..] == hash[..]) {
+                return Ok(());
+            }
+        }
+        Err(PinMismatch {
+            host: host.to_owned(),
+        })
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase()
+}
+
+/// Returns the DER `SubjectPublicKeyInfo` of a DER X.509 certificate.
+fn subject_public_key_info(cert: &[u8]) -> Option<&[u8]> {
+    const SEQUENCE: u8 = 0x30;
+    const VERSION: u8 = 0xa0;
+
+    let (tag, cert, _) = split_der(cert)?;
+    if tag != SEQUENCE {
+        return None;
+    }
+    let (tag, mut tbs, _) = split_der(cert)?;
+    if tag != SEQUENCE {
+        return None;
+    }
+    if tbs.first() == Some(&VERSION) {
+        tbs = split_der(tbs)?.2;
+    }
+    // serialNumber, signature, issuer, validity and subject
+    for _ in 0..5 {
+        tbs = split_der(tbs)?.2;
+    }
+    let (tag, _, rest) = split_der(tbs)?;
+    if tag != SEQUENCE {
+        return None;
+    }
+    Some(&tbs[..tbs.len() - rest.len()])
+}
+
+/// Splits the first DER element off `der`, returning its tag, its contents
+/// and the elements after it.
+fn split_der(der: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *der.first()?;
+    let first = *der.get(1)?;
+    let (len, start) = if first < 0x80 {
+        (first as usize, 2)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 {
+            return None;
+        }
+        let len = der
+            .get(2..2 + n)?
+            .iter()
+            .fold(0, |len, &b| len << 8 | b as usize);
+        (len, 2 + n)
+    };
+    let end = start.checked_add(len)?;
+    Some((tag, der.get(start..end)?, &der[end..]))
+}
+
+/// The certificate of a server doesn't match the pins of its host.
 #[derive(Debug)]
+pub(crate) struct PinMismatch {
+    host: String,
+}
+
+impl fmt::Display for PinMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "certificate of {} doesn't match any pinned public key",
+            self.host
+        )
+    }
+}
+
+impl std::error::Error for PinMismatch {}
+
 pub(crate) enum TlsBackend {
     #[cfg(feature = "default-tls")]
     Default,
-    #[cfg(feature = "rustls-tls")]
+    #[cfg(feature = "default-tls")]
+    BuiltNativeTls(native_tls_crate::TlsConnector),
+    #[cfg(feature = "__rustls")]
     Rustls,
+    #[cfg(feature = "__rustls")]
+    BuiltRustls(rustls::ClientConfig),
+    UnknownPreconfigured,
+}
+
+impl fmt::Debug for TlsBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "default-tls")]
+            TlsBackend::Default => write!(f, "Default"),
+            #[cfg(feature = "default-tls")]
+            TlsBackend::BuiltNativeTls(_) => write!(f, "BuiltNativeTls"),
+            #[cfg(feature = "__rustls")]
+            TlsBackend::Rustls => write!(f, "Rustls"),
+            #[cfg(feature = "__rustls")]
+            TlsBackend::BuiltRustls(_) => write!(f, "BuiltRustls"),
+            TlsBackend::UnknownPreconfigured => write!(f, "UnknownPreconfigured"),
+        }
+    }
 }
 
 impl Default for TlsBackend {
@@ -281,17 +471,17 @@ impl Default for TlsBackend {
             TlsBackend::Default
         }
 
-        #[cfg(all(feature = "rustls-tls", not(feature = "default-tls")))]
+        #[cfg(all(feature = "__rustls", not(feature = "default-tls")))]
         {
             TlsBackend::Rustls
         }
     }
 }
 
-#[cfg(feature = "rustls-tls")]
+#[cfg(feature = "__rustls")]
 pub(crate) struct NoVerifier;
 
-#[cfg(feature = "rustls-tls")]
+#[cfg(feature = "__rustls")]
 impl ServerCertVerifier for NoVerifier {
     fn verify_server_cert(
         &self,
@@ -308,6 +498,35 @@ impl ServerCertVerifier for NoVerifier {
 mod tests {
     use super::*;
 
+    fn der(pem: &[u8]) -> Vec<u8> {
+        let pem = std::str::from_utf8(pem).unwrap();
+        let base64 = pem
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect::<String>();
+        base64::decode(&base64).unwrap()
+    }
+
+    #[test]
+    fn certificate_pins_verify() {
+        let cert = der(include_bytes!("../tests/support/certs/server.pem"));
+        let spki = subject_public_key_info(&cert).unwrap();
+        let mut pins = CertificatePins::default();
+        pins.add("Localhost", Sha256::digest(spki).into());
+
+        pins.verify("localhost", Some(&cert)).unwrap();
+        pins.verify("example.com", Some(&cert)).unwrap();
+        pins.verify("localhost", Some(&cert[..100])).unwrap_err();
+        pins.verify("localhost", None).unwrap_err();
+
+        let ca = der(include_bytes!("../tests/support/certs/ca.pem"));
+        let err = pins.verify("localhost", Some(&ca)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "certificate of localhost doesn't match any pinned public key"
+        );
+    }
+
     #[cfg(feature = "default-tls")]
     #[test]
     fn certificate_from_der_invalid() {
@@ -326,13 +545,13 @@ mod tests {
         Identity::from_pkcs12_der(b"not der", "nope").unwrap_err();
     }
 
-    #[cfg(feature = "rustls-tls")]
+    #[cfg(feature = "__rustls")]
     #[test]
     fn identity_from_pem_invalid() {
         Identity::from_pem(b"not pem").unwrap_err();
     }
 
-    #[cfg(feature = "rustls-tls")]
+    #[cfg(feature = "__rustls")]
     #[test]
     fn identity_from_pem_pkcs1_key() {
         let pem = b"-----BEGIN CERTIFICATE-----\n\
@@ -52,6 +52,59 @@ fn test_response_json() {
     assert_eq!("Hello", body);
 }
 
+#[test]
+#[cfg(feature = "zstd")]
+fn test_response_zstd() {
+    let server = server::http(move |_req| {
+        async {
+            let compressed = zstd_crate::encode_all(&b"Hello"[..], 3).unwrap();
+            http::Response::builder()
+                .header("content-encoding", "zstd")
+                .body(compressed.into())
+                .unwrap()
+        }
+    });
+
+    let url = format!("http://{}/zstd", server.addr());
+    let res = reqwest::blocking::get(&url).unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.headers().get("content-encoding"), None);
+
+    let body = res.text().unwrap();
+    assert_eq!("Hello", body);
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn test_compress_request_body() {
+    use std::io::Read;
+
+    let server = server::http(move |req| {
+        async move {
+            assert_eq!(req.headers()["content-encoding"], "gzip");
+            assert_eq!(req.headers().get("content-length"), None);
+
+            let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let mut decoder = libflate::gzip::Decoder::new(&body[..]).unwrap();
+            let mut decoded = String::new();
+            decoder.read_to_string(&mut decoded).unwrap();
+            assert_eq!(decoded, "hello compressed world");
+
+            http::Response::default()
+        }
+    });
+
+    let body = reqwest::blocking::Body::new(&b"hello compressed world"[..]);
+    let res = reqwest::blocking::Client::new()
+        .post(&format!("http://{}/compress", server.addr()))
+        .body(body)
+        .compress(reqwest::Encoding::Gzip)
+        .send()
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
 #[test]
 fn test_response_copy_to() {
     let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
@@ -301,3 +354,114 @@ fn test_blocking_inside_a_runtime() {
         let _should_panic = reqwest::blocking::get(&url);
     });
 }
+
+#[test]
+fn test_retry_policy() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server = server::http({
+        let hits = hits.clone();
+        move |req| {
+            let first = hits.fetch_add(1, Ordering::SeqCst) == 0;
+            async move {
+                let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                assert_eq!(body, "retry me");
+                if first {
+                    http::Response::builder()
+                        .status(503)
+                        .body(Default::default())
+                        .unwrap()
+                } else {
+                    http::Response::default()
+                }
+            }
+        }
+    });
+
+    let policy = reqwest::retry::Policy::limited(1)
+        .backoff(Duration::from_millis(1), Duration::from_millis(10));
+    let url = format!("http://{}/retry", server.addr());
+    let res = reqwest::blocking::Client::builder()
+        .retry(policy)
+        .build()
+        .unwrap()
+        .put(&url)
+        .body("retry me")
+        .send()
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_middleware() {
+    use std::sync::Arc;
+
+    use reqwest::header::HeaderValue;
+    use reqwest::middleware::Middleware;
+
+    struct Sign;
+
+    impl Middleware for Sign {
+        fn on_request(&self, req: &mut reqwest::Request) -> Option<reqwest::Response> {
+            req.headers_mut()
+                .insert("x-signature", HeaderValue::from_static("signed"));
+            None
+        }
+
+        fn on_response(&self, res: &mut reqwest::Response) {
+            res.headers_mut()
+                .insert("x-verified", HeaderValue::from_static("true"));
+        }
+    }
+
+    let server = server::http(move |req| {
+        async move {
+            assert_eq!(req.headers()["x-signature"], "signed");
+            http::Response::default()
+        }
+    });
+
+    let url = format!("http://{}/middleware", server.addr());
+    let res = reqwest::blocking::Client::builder()
+        .middleware(Arc::new(Sign))
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.headers()["x-verified"], "true");
+}
+
+#[test]
+fn test_stats() {
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let client = reqwest::blocking::Client::new();
+    let url = format!("http://{}/stats", server.addr());
+    let res = client.get(&url).send().unwrap();
+    assert_eq!(res.text().unwrap(), "Hello");
+
+    let stats = client.stats();
+    assert_eq!(stats.connections_created(), 1);
+    assert_eq!(stats.requests_in_flight(), 0);
+    assert!(stats.bytes_received() > 0);
+}
+
+#[test]
+fn test_timings() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let url = format!("http://{}/timings", server.addr());
+    let res = reqwest::blocking::get(&url).unwrap();
+
+    let timings = res.timings();
+    assert!(timings.connect().is_some());
+    assert!(timings.total() >= timings.ttfb());
+}
@@ -0,0 +1,189 @@
+mod support;
+use support::*;
+
+use std::io::Read;
+
+#[tokio::test]
+async fn brotli_response() {
+    brotli_case(10_000, 4096).await;
+}
+
+#[tokio::test]
+async fn brotli_single_byte_chunks() {
+    brotli_case(10, 1).await;
+}
+
+#[tokio::test]
+async fn test_brotli_empty_body() {
+    let server = server::http(move |req| {
+        async move {
+            assert_eq!(req.method(), "HEAD");
+
+            http::Response::builder()
+                .header("content-encoding", "br")
+                .header("content-length", 100)
+                .body(Default::default())
+                .unwrap()
+        }
+    });
+
+    let client = reqwest::Client::new();
+    let res = client
+        .head(&format!("http://{}/brotli", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    let body = res.text().await.unwrap();
+
+    assert_eq!(body, "");
+}
+
+#[tokio::test]
+async fn test_accept_header_is_not_changed_if_set() {
+    let server = server::http(move |req| {
+        async move {
+            assert_eq!(req.headers()["accept"], "application/json");
+            assert_eq!(req.headers()["accept-encoding"], "br");
+            http::Response::default()
+        }
+    });
+
+    let client = reqwest::Client::builder()
+        .no_gzip()
+        .no_deflate()
+        .no_zstd()
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(&format!("http://{}/accept", server.addr()))
+        .header(
+            reqwest::header::ACCEPT,
+            reqwest::header::HeaderValue::from_static("application/json"),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_accept_encoding_header_is_not_changed_if_set() {
+    let server = server::http(move |req| {
+        async move {
+            assert_eq!(req.headers()["accept"], "*/*");
+            assert_eq!(req.headers()["accept-encoding"], "identity");
+            http::Response::default()
+        }
+    });
+
+    let client = reqwest::Client::new();
+
+    let res = client
+        .get(&format!("http://{}/accept-encoding", server.addr()))
+        .header(
+            reqwest::header::ACCEPT_ENCODING,
+            reqwest::header::HeaderValue::from_static("identity"),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_compress_request_body() {
+    let server = server::http(move |req| {
+        async move {
+            assert_eq!(req.headers()["content-encoding"], "br");
+            let len = req.headers()["content-length"].to_str().unwrap().parse::<usize>();
+
+            let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            assert_eq!(len, Ok(body.len()));
+
+            let mut decoder = brotli_crate::Decompressor::new(&body[..], 4096);
+            let mut decoded = String::new();
+            decoder.read_to_string(&mut decoded).unwrap();
+            assert_eq!(decoded, "hello compressed world");
+
+            http::Response::default()
+        }
+    });
+
+    let res = reqwest::Client::new()
+        .post(&format!("http://{}/compress", server.addr()))
+        .body("hello compressed world")
+        .compress(reqwest::Encoding::Brotli)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+async fn brotli_case(response_size: usize, chunk_size: usize) {
+    use futures_util::stream::StreamExt;
+
+    let content: String = (0..response_size)
+        .into_iter()
+        .map(|i| format!("test {}", i))
+        .collect();
+    let mut encoder = brotli_crate::CompressorReader::new(content.as_bytes(), 4096, 5, 20);
+    let mut compressed_content = Vec::new();
+    encoder.read_to_end(&mut compressed_content).unwrap();
+
+    let mut response = format!(
+        "\
+         HTTP/1.1 200 OK\r\n\
+         Server: test-accept\r\n\
+         Content-Encoding: br\r\n\
+         Content-Length: {}\r\n\
+         \r\n",
+        &compressed_content.len()
+    )
+    .into_bytes();
+    response.extend(&compressed_content);
+
+    let server = server::http(move |req| {
+        assert_eq!(req.headers()["accept-encoding"], "br");
+
+        let compressed = compressed_content.clone();
+        async move {
+            let len = compressed.len();
+            let stream = futures_util::stream::unfold((compressed, 0), move |(compressed, pos)| {
+                async move {
+                    let chunk = compressed.chunks(chunk_size).nth(pos)?.to_vec();
+
+                    Some((chunk, (compressed, pos + 1)))
+                }
+            });
+
+            let body = hyper::Body::wrap_stream(stream.map(Ok::<_, std::convert::Infallible>));
+
+            http::Response::builder()
+                .header("content-encoding", "br")
+                .header("content-length", len)
+                .body(body)
+                .unwrap()
+        }
+    });
+
+    let client = reqwest::Client::builder()
+        .no_gzip()
+        .no_deflate()
+        .no_zstd()
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(&format!("http://{}/brotli", server.addr()))
+        .send()
+        .await
+        .expect("response");
+
+    let body = res.text().await.expect("text");
+    assert_eq!(body, content);
+}
@@ -2,6 +2,11 @@ mod support;
 use futures_util::stream::StreamExt;
 use support::*;
 
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::dns::{AddressFamily, Addrs, Name, Resolve, Resolving};
 use reqwest::Client;
 
 #[tokio::test]
@@ -12,8 +17,23 @@ async fn auto_headers() {
 
             assert_eq!(req.headers()["accept"], "*/*");
             assert_eq!(req.headers().get("user-agent"), None);
+            let mut accepts = Vec::new();
             if cfg!(feature = "gzip") {
-                assert_eq!(req.headers()["accept-encoding"], "gzip");
+                accepts.push("gzip");
+            }
+            if cfg!(feature = "brotli") {
+                accepts.push("br");
+            }
+            if cfg!(feature = "deflate") {
+                accepts.push("deflate");
+            }
+            if cfg!(feature = "zstd") {
+                accepts.push("zstd");
+            }
+            if accepts.is_empty() {
+                assert_eq!(req.headers().get("accept-encoding"), None);
+            } else {
+                assert_eq!(req.headers()["accept-encoding"], accepts.join(", ").as_str());
             }
 
             http::Response::default()
@@ -130,3 +150,368 @@ async fn body_pipe_response() {
 
     assert_eq!(res2.status(), reqwest::StatusCode::OK);
 }
+
+/// Resolves every name to localhost, recording which names were looked up.
+struct MockResolver {
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl Resolve for MockResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        self.calls.lock().unwrap().push(name.as_str().to_owned());
+        let addrs: Addrs = Box::new(vec![IpAddr::from([127, 0, 0, 1])].into_iter());
+        Box::pin(async move { Ok(addrs) })
+    }
+}
+
+#[tokio::test]
+async fn dns_resolver_direct() {
+    let server = server::http(move |req| {
+        async move {
+            assert_eq!(req.uri(), "/dns");
+            assert!(req.headers()["host"]
+                .to_str()
+                .unwrap()
+                .starts_with("mocked.invalid:"));
+            http::Response::default()
+        }
+    });
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let url = format!("http://mocked.invalid:{}/dns", server.addr().port());
+    let res = reqwest::Client::builder()
+        .no_proxy()
+        .dns_resolver(Arc::new(MockResolver {
+            calls: calls.clone(),
+        }))
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .expect("request");
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.remote_addr(), Some(server.addr()));
+    assert_eq!(*calls.lock().unwrap(), vec!["mocked.invalid"]);
+}
+
+#[tokio::test]
+async fn dns_resolver_proxy() {
+    let url = "http://hyper.rs/prox";
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), url);
+        async { http::Response::default() }
+    });
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let proxy = format!("http://proxy.invalid:{}", server.addr().port());
+    let res = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(&proxy).unwrap())
+        .dns_resolver(Arc::new(MockResolver {
+            calls: calls.clone(),
+        }))
+        .build()
+        .expect("client builder")
+        .get(url)
+        .send()
+        .await
+        .expect("request");
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(*calls.lock().unwrap(), vec!["proxy.invalid"]);
+}
+
+#[tokio::test]
+async fn resolve_overrides_host() {
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), "/resolve");
+        async { http::Response::default() }
+    });
+
+    let port = server.addr().port();
+    let url = format!("http://override.invalid:{}/resolve", port);
+    // The port in the override is ignored, the URL's port is used.
+    let addr = SocketAddr::new(server.addr().ip(), 1);
+    let res = reqwest::Client::builder()
+        .no_proxy()
+        .resolve("Override.Invalid", addr)
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .expect("request");
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.url().as_str(), &url);
+}
+
+/// Resolves every name to an unreachable IPv6 address, then localhost.
+struct DualStackResolver;
+
+impl Resolve for DualStackResolver {
+    fn resolve(&self, _name: Name) -> Resolving {
+        // 100::1 is in the discard-only prefix, so connecting to it stalls
+        // or fails.
+        let addrs: Addrs = Box::new(
+            vec![
+                IpAddr::from([0x100, 0, 0, 0, 0, 0, 0, 1]),
+                IpAddr::from([127, 0, 0, 1]),
+            ]
+            .into_iter(),
+        );
+        Box::pin(async move { Ok(addrs) })
+    }
+}
+
+#[tokio::test]
+async fn happy_eyeballs_falls_back() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let url = format!("http://dual-stack.invalid:{}/", server.addr().port());
+    let res = reqwest::Client::builder()
+        .no_proxy()
+        .dns_resolver(Arc::new(DualStackResolver))
+        .happy_eyeballs_timeout(Duration::from_millis(50))
+        .connect_timeout(Duration::from_secs(10))
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .expect("request");
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.remote_addr(), Some(server.addr()));
+}
+
+#[tokio::test]
+async fn address_family_prefer_ipv4() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let url = format!("http://dual-stack.invalid:{}/", server.addr().port());
+    let res = reqwest::Client::builder()
+        .no_proxy()
+        .dns_resolver(Arc::new(DualStackResolver))
+        .address_family(AddressFamily::PreferIpv4)
+        .happy_eyeballs_timeout(None)
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .expect("request");
+
+    assert!(res.remote_addr().unwrap().is_ipv4());
+}
+
+#[tokio::test]
+async fn address_family_ipv6_only() {
+    let url = "http://mocked.invalid/";
+    let err = reqwest::Client::builder()
+        .no_proxy()
+        .dns_resolver(Arc::new(MockResolver {
+            calls: Default::default(),
+        }))
+        .address_family(AddressFamily::Ipv6Only)
+        .build()
+        .expect("client builder")
+        .get(url)
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_resolve());
+}
+
+#[tokio::test]
+async fn resolve_overrides_redirect_target() {
+    let server = server::http(move |req| async move {
+        let host = req.headers()["host"].to_str().unwrap().to_owned();
+        if req.uri() == "/start" {
+            assert!(host.starts_with("first.invalid:"), "host: {}", host);
+            let port = host.rsplit(':').next().unwrap();
+            http::Response::builder()
+                .status(302)
+                .header("location", format!("http://second.invalid:{}/dst", port))
+                .body(Default::default())
+                .unwrap()
+        } else {
+            assert_eq!(req.uri(), "/dst");
+            assert!(host.starts_with("second.invalid:"), "host: {}", host);
+            http::Response::default()
+        }
+    });
+
+    let port = server.addr().port();
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .resolve("first.invalid", server.addr())
+        .resolve_to_addrs("second.invalid", &[server.addr()])
+        .build()
+        .expect("client builder");
+
+    // Run twice so the second round goes over pooled connections.
+    for _ in 0..2 {
+        let res = client
+            .get(&format!("http://first.invalid:{}/start", port))
+            .send()
+            .await
+            .expect("request");
+
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            res.url().as_str(),
+            format!("http://second.invalid:{}/dst", port)
+        );
+    }
+}
+
+struct FailingResolver;
+
+impl Resolve for FailingResolver {
+    fn resolve(&self, _name: Name) -> Resolving {
+        Box::pin(async { Err("no such host".into()) })
+    }
+}
+
+#[tokio::test]
+async fn dns_resolver_error_is_resolve() {
+    let err = reqwest::Client::builder()
+        .no_proxy()
+        .dns_resolver(Arc::new(FailingResolver))
+        .build()
+        .expect("client builder")
+        .get("http://fails.invalid/")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_resolve(), "{:?}", err);
+    assert_eq!(err.url().map(|u| u.as_str()), Some("http://fails.invalid/"));
+    assert!(err.to_string().contains("no such host"), "{}", err);
+}
+
+#[cfg(feature = "trust-dns")]
+#[tokio::test]
+async fn trust_dns_resolves_localhost() {
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), "/trust-dns");
+        async { http::Response::default() }
+    });
+
+    let url = format!("http://localhost:{}/trust-dns", server.addr().port());
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .expect("client builder");
+
+    // The second request is answered from the resolver's cache.
+    for _ in 0..2 {
+        let res = client.get(&url).send().await.expect("request");
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn client_is_a_service() {
+    use hyper::service::Service;
+
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), "/service");
+        async { http::Response::default() }
+    });
+
+    let url = format!("http://{}/service", server.addr());
+    let mut client = Client::new();
+
+    futures_util::future::poll_fn(|cx| client.poll_ready(cx))
+        .await
+        .expect("ready");
+    let req = client.get(&url).build().expect("request");
+    let res = client.call(req).await.expect("response");
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let req = client.get(&url).build().expect("request");
+    let res = (&client).call(req).await.expect("response");
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn tcp_options() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let url = format!("http://{}/tcp", server.addr());
+    let res = reqwest::Client::builder()
+        .tcp_keepalive(Duration::from_secs(60))
+        .tcp_send_buffer_size(64 * 1024)
+        .tcp_recv_buffer_size(64 * 1024)
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .expect("request");
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn interface_loopback() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let url = format!("http://localhost:{}/interface", server.addr().port());
+    let res = reqwest::Client::builder()
+        .no_proxy()
+        .interface("lo")
+        .tcp_keepalive(Duration::from_secs(60))
+        .address_family(AddressFamily::Ipv4Only)
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .expect("request");
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.remote_addr(), Some(server.addr()));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn interface_unknown() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let url = format!("http://{}/interface", server.addr());
+    let err = reqwest::Client::builder()
+        .interface("reqwest-none0")
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .unwrap_err();
+
+    // Binding the socket fails with `ENODEV`.
+    assert_eq!(err.url().map(|u| u.as_str()), Some(url.as_str()));
+    let mut source = std::error::Error::source(&err);
+    let mut found = false;
+    while let Some(e) = source {
+        found |= e.downcast_ref::<std::io::Error>().is_some();
+        source = e.source();
+    }
+    assert!(found, "no io::Error in {:?}", err);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn interface_invalid_name() {
+    let err = reqwest::Client::builder()
+        .interface("lo\0")
+        .build()
+        .unwrap_err();
+
+    assert!(err.is_builder());
+}
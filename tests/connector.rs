@@ -0,0 +1,149 @@
+mod support;
+use support::*;
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use http::Uri;
+use hyper::service::Service;
+use tokio::net::TcpStream;
+
+/// Connects to `addr` whatever the URI, and records the URIs it was called
+/// with.
+#[derive(Clone)]
+struct FixedConnector {
+    addr: SocketAddr,
+    uris: Arc<Mutex<Vec<Uri>>>,
+}
+
+impl FixedConnector {
+    fn new(addr: SocketAddr) -> FixedConnector {
+        FixedConnector {
+            addr,
+            uris: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+impl Service<Uri> for FixedConnector {
+    type Response = TcpStream;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        self.uris.lock().unwrap().push(dst);
+        Box::pin(TcpStream::connect(self.addr))
+    }
+}
+
+#[tokio::test]
+async fn custom_connector() {
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), "/custom");
+        assert_eq!(req.headers()["host"], "custom.invalid");
+        async { http::Response::default() }
+    });
+
+    let connector = FixedConnector::new(server.addr());
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .connector(connector.clone())
+        .build()
+        .unwrap();
+
+    let res = client
+        .get("http://custom.invalid/custom")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        *connector.uris.lock().unwrap(),
+        vec![Uri::from_static("http://custom.invalid/")]
+    );
+}
+
+#[tokio::test]
+async fn custom_connector_with_http_proxy() {
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), "http://custom.invalid/proxied");
+        async { http::Response::default() }
+    });
+
+    let connector = FixedConnector::new(server.addr());
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http("http://proxy.invalid:8080").unwrap())
+        .connector(connector.clone())
+        .build()
+        .unwrap();
+
+    let res = client
+        .get("http://custom.invalid/proxied")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let uris = connector.uris.lock().unwrap();
+    assert_eq!(uris.len(), 1);
+    assert_eq!(uris[0].host(), Some("proxy.invalid"));
+    assert_eq!(uris[0].port_u16(), Some(8080));
+}
+
+#[derive(Clone)]
+struct FailingConnector;
+
+impl Service<Uri> for FailingConnector {
+    type Response = TcpStream;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _dst: Uri) -> Self::Future {
+        Box::pin(async {
+            Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                "no route to sidecar",
+            ))
+        })
+    }
+}
+
+#[tokio::test]
+async fn custom_connector_error() {
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .connector(FailingConnector)
+        .build()
+        .unwrap();
+
+    let err = client
+        .get("http://custom.invalid/")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.url().map(|u| u.as_str()),
+        Some("http://custom.invalid/")
+    );
+    let mut source = std::error::Error::source(&err);
+    let mut found = false;
+    while let Some(e) = source {
+        found |= e.to_string().contains("no route to sidecar");
+        source = e.source();
+    }
+    assert!(found, "{:?}", err);
+}
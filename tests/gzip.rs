@@ -1,7 +1,7 @@
 mod support;
 use support::*;
 
-use std::io::Write;
+use std::io::{Read, Write};
 
 #[tokio::test]
 async fn gzip_response() {
@@ -49,7 +49,12 @@ async fn test_accept_header_is_not_changed_if_set() {
         }
     });
 
-    let client = reqwest::Client::new();
+    let client = reqwest::Client::builder()
+        .no_brotli()
+        .no_deflate()
+        .no_zstd()
+        .build()
+        .unwrap();
 
     let res = client
         .get(&format!("http://{}/accept", server.addr()))
@@ -89,6 +94,66 @@ async fn test_accept_encoding_header_is_not_changed_if_set() {
     assert_eq!(res.status(), reqwest::StatusCode::OK);
 }
 
+#[tokio::test]
+async fn test_compress_request_body() {
+    let server = server::http(move |req| {
+        async move {
+            assert_eq!(req.headers()["content-encoding"], "gzip");
+            let len = req.headers()["content-length"].to_str().unwrap().parse::<usize>();
+
+            let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            assert_eq!(len, Ok(body.len()));
+
+            let mut decoder = libflate::gzip::Decoder::new(&body[..]).unwrap();
+            let mut decoded = String::new();
+            decoder.read_to_string(&mut decoded).unwrap();
+            assert_eq!(decoded, "hello compressed world");
+
+            http::Response::default()
+        }
+    });
+
+    let res = reqwest::Client::new()
+        .post(&format!("http://{}/compress", server.addr()))
+        .body("hello compressed world")
+        .compress(reqwest::Encoding::Gzip)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn test_compress_streaming_request_body() {
+    let server = server::http(move |req| {
+        async move {
+            assert_eq!(req.headers()["content-encoding"], "gzip");
+            assert_eq!(req.headers().get("content-length"), None);
+
+            let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let mut decoder = libflate::gzip::Decoder::new(&body[..]).unwrap();
+            let mut decoded = String::new();
+            decoder.read_to_string(&mut decoded).unwrap();
+            assert_eq!(decoded, "hello streaming world");
+
+            http::Response::default()
+        }
+    });
+
+    let chunks: Vec<Result<_, std::io::Error>> = vec![Ok("hello "), Ok("streaming "), Ok("world")];
+    let res = reqwest::Client::new()
+        .post(&format!("http://{}/compress", server.addr()))
+        .body(reqwest::Body::wrap_stream(futures_util::stream::iter(chunks)))
+        .compress(reqwest::Encoding::Gzip)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
 async fn gzip_case(response_size: usize, chunk_size: usize) {
     use futures_util::stream::StreamExt;
 
@@ -140,7 +205,12 @@ async fn gzip_case(response_size: usize, chunk_size: usize) {
         }
     });
 
-    let client = reqwest::Client::new();
+    let client = reqwest::Client::builder()
+        .no_brotli()
+        .no_deflate()
+        .no_zstd()
+        .build()
+        .unwrap();
 
     let res = client
         .get(&format!("http://{}/gzip", server.addr()))
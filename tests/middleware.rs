@@ -0,0 +1,165 @@
+mod support;
+use support::*;
+
+use std::sync::{Arc, Mutex};
+
+use reqwest::header::HeaderValue;
+use reqwest::middleware::Middleware;
+use reqwest::{Request, Response, StatusCode};
+
+/// Adds a header to every request, and records the URLs and statuses seen.
+#[derive(Default)]
+struct Recorder {
+    requests: Mutex<Vec<String>>,
+    responses: Mutex<Vec<StatusCode>>,
+}
+
+impl Middleware for Recorder {
+    fn on_request(&self, req: &mut Request) -> Option<Response> {
+        req.headers_mut()
+            .insert("x-middleware", HeaderValue::from_static("yes"));
+        self.requests
+            .lock()
+            .unwrap()
+            .push(req.url().path().to_owned());
+        None
+    }
+
+    fn on_response(&self, res: &mut Response) {
+        self.responses.lock().unwrap().push(res.status());
+    }
+}
+
+/// Answers every request itself, without sending it.
+struct ShortCircuit;
+
+impl Middleware for ShortCircuit {
+    fn on_request(&self, _req: &mut Request) -> Option<Response> {
+        let res = http::Response::builder()
+            .status(StatusCode::IM_A_TEAPOT)
+            .body("short-circuited")
+            .unwrap();
+        Some(res.into())
+    }
+}
+
+#[tokio::test]
+async fn middleware_sees_request_and_response() {
+    let server = server::http(move |req| {
+        async move {
+            assert_eq!(req.headers()["x-middleware"], "yes");
+            http::Response::default()
+        }
+    });
+
+    let recorder = Arc::new(Recorder::default());
+    let client = reqwest::Client::builder()
+        .middleware(recorder.clone())
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/middleware", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(*recorder.requests.lock().unwrap(), vec!["/middleware"]);
+    assert_eq!(*recorder.responses.lock().unwrap(), vec![StatusCode::OK]);
+}
+
+#[tokio::test]
+async fn middleware_sees_redirects() {
+    let server = server::http(move |req| {
+        async move {
+            assert_eq!(req.headers()["x-middleware"], "yes");
+            if req.uri() == "/redirect" {
+                http::Response::builder()
+                    .status(302)
+                    .header("location", "/dst")
+                    .body(Default::default())
+                    .unwrap()
+            } else {
+                assert_eq!(req.uri(), "/dst");
+                http::Response::default()
+            }
+        }
+    });
+
+    let recorder = Arc::new(Recorder::default());
+    let client = reqwest::Client::builder()
+        .middleware(recorder.clone())
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/redirect", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.url().path(), "/dst");
+    assert_eq!(*recorder.requests.lock().unwrap(), vec!["/redirect", "/dst"]);
+    assert_eq!(*recorder.responses.lock().unwrap(), vec![StatusCode::OK]);
+}
+
+#[tokio::test]
+async fn middleware_short_circuits() {
+    let server = server::http(move |_req| async { panic!("request should not be sent") });
+
+    let first = Arc::new(Recorder::default());
+    let last = Arc::new(Recorder::default());
+    let client = reqwest::Client::builder()
+        .middleware(first.clone())
+        .middleware(Arc::new(ShortCircuit))
+        .middleware(last.clone())
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/short-circuit", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+
+    assert_eq!(res.status(), StatusCode::IM_A_TEAPOT);
+    assert_eq!(res.text().await.unwrap(), "short-circuited");
+    assert_eq!(*first.responses.lock().unwrap(), vec![StatusCode::IM_A_TEAPOT]);
+    assert!(last.requests.lock().unwrap().is_empty());
+    assert!(last.responses.lock().unwrap().is_empty());
+}
+
+/// Replaces the body of the requests following a redirect.
+struct ReplaceBody;
+
+impl Middleware for ReplaceBody {
+    fn on_request(&self, req: &mut Request) -> Option<Response> {
+        if req.url().path() == "/dst" {
+            *req.body_mut() = Some("replaced".into());
+        }
+        None
+    }
+}
+
+#[tokio::test]
+async fn middleware_modifies_redirect() {
+    let server = server::http(move |req| {
+        async move {
+            if req.uri() == "/redirect" {
+                http::Response::builder()
+                    .status(307)
+                    .header("location", "/dst")
+                    .body(Default::default())
+                    .unwrap()
+            } else {
+                let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                assert_eq!(body, "replaced");
+                http::Response::default()
+            }
+        }
+    });
+
+    let client = reqwest::Client::builder()
+        .middleware(Arc::new(ReplaceBody))
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/redirect", server.addr());
+    let res = client.post(&url).body("original").send().await.unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.url().path(), "/dst");
+}
@@ -0,0 +1,159 @@
+mod support;
+use support::*;
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use http::Uri;
+use hyper::service::Service;
+use tokio::net::TcpStream;
+
+/// Connects to `addr`, and counts the connections it opened.
+#[derive(Clone)]
+struct CountingConnector {
+    addr: SocketAddr,
+    connects: Arc<AtomicUsize>,
+}
+
+impl CountingConnector {
+    fn new(addr: SocketAddr) -> CountingConnector {
+        CountingConnector {
+            addr,
+            connects: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn connects(&self) -> usize {
+        self.connects.load(Ordering::SeqCst)
+    }
+}
+
+impl Service<Uri> for CountingConnector {
+    type Response = TcpStream;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _dst: Uri) -> Self::Future {
+        self.connects.fetch_add(1, Ordering::SeqCst);
+        Box::pin(TcpStream::connect(self.addr))
+    }
+}
+
+async fn get(client: &reqwest::Client, url: &str) {
+    let res = client.get(url).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    res.text().await.unwrap();
+    // Give the connection time to go back to the pool.
+    tokio::time::delay_for(Duration::from_millis(50)).await;
+}
+
+#[tokio::test]
+async fn pool_reuses_idle_connections() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let connector = CountingConnector::new(server.addr());
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .connector(connector.clone())
+        .build()
+        .unwrap();
+
+    get(&client, "http://pool.invalid/1").await;
+    get(&client, "http://pool.invalid/2").await;
+
+    assert_eq!(connector.connects(), 1);
+}
+
+#[tokio::test]
+async fn pool_max_idle_per_host() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let connector = CountingConnector::new(server.addr());
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .connector(connector.clone())
+        .pool_max_idle_per_host(0)
+        .build()
+        .unwrap();
+
+    get(&client, "http://pool.invalid/1").await;
+    get(&client, "http://pool.invalid/2").await;
+
+    assert_eq!(connector.connects(), 2);
+}
+
+#[tokio::test]
+async fn pool_idle_timeout() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let connector = CountingConnector::new(server.addr());
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .connector(connector.clone())
+        .pool_idle_timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    get(&client, "http://pool.invalid/1").await;
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+    get(&client, "http://pool.invalid/2").await;
+
+    assert_eq!(connector.connects(), 2);
+}
+
+#[tokio::test]
+async fn close_idle_connections() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let connector = CountingConnector::new(server.addr());
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .connector(connector.clone())
+        .build()
+        .unwrap();
+
+    get(&client, "http://pool.invalid/1").await;
+    client.close_idle_connections();
+    get(&client, "http://pool.invalid/2").await;
+    get(&client, "http://pool.invalid/3").await;
+
+    assert_eq!(connector.connects(), 2);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn close_idle_connections_blocking() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let connector = CountingConnector::new(server.addr());
+    let client = reqwest::blocking::Client::builder()
+        .no_proxy()
+        .connector(connector.clone())
+        .build()
+        .unwrap();
+
+    let get = |url| {
+        let res = client.get(url).send().unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        res.text().unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+    };
+
+    get("http://pool.invalid/1");
+    get("http://pool.invalid/2");
+    assert_eq!(connector.connects(), 1);
+
+    client.close_idle_connections();
+    get("http://pool.invalid/3");
+    assert_eq!(connector.connects(), 2);
+}
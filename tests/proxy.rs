@@ -2,6 +2,7 @@ mod support;
 use support::*;
 
 use std::env;
+use std::sync::{Arc, Mutex};
 
 #[tokio::test]
 async fn http_proxy() {
@@ -119,6 +120,35 @@ async fn test_no_proxy() {
     assert_eq!(res.status(), reqwest::StatusCode::OK);
 }
 
+#[tokio::test]
+async fn test_proxy_no_proxy_excludes_host() {
+    let server = server::http(move |req| {
+        assert_eq!(req.method(), "GET");
+        assert_eq!(req.uri(), "/5");
+
+        async { http::Response::default() }
+    });
+    let proxy = format!("http://{}", server.addr());
+    let url = format!("http://{}/5", server.addr());
+
+    // the proxy would see an absolute-form uri, so "/5" means we went direct.
+    let res = reqwest::Client::builder()
+        .proxy(
+            reqwest::Proxy::http(&proxy)
+                .unwrap()
+                .no_proxy(reqwest::NoProxy::from_string("localhost, 127.0.0.0/8")),
+        )
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.url().as_str(), &url);
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
 #[cfg_attr(not(feature = "__internal_proxy_sys_no_cache"), ignore)]
 #[tokio::test]
 async fn test_using_system_proxy() {
@@ -178,3 +208,141 @@ async fn http_over_http() {
     assert_eq!(res.url().as_str(), url);
     assert_eq!(res.status(), reqwest::StatusCode::OK);
 }
+
+/// Answers requests without a `Proxy-Authorization` header with a Digest
+/// challenge, and records the headers of the others.
+fn digest_proxy(authorized: Arc<Mutex<Vec<String>>>) -> server::Server {
+    server::http(move |req| {
+        let authorized = authorized.clone();
+        async move {
+            match req.headers().get("proxy-authorization") {
+                None => http::Response::builder()
+                    .status(407)
+                    .header(
+                        "proxy-authenticate",
+                        r#"Digest realm="proxy", nonce="abc", qop="auth""#,
+                    )
+                    .body(Default::default())
+                    .unwrap(),
+                Some(auth) => {
+                    authorized
+                        .lock()
+                        .unwrap()
+                        .push(auth.to_str().unwrap().to_owned());
+                    http::Response::default()
+                }
+            }
+        }
+    })
+}
+
+#[tokio::test]
+async fn http_proxy_digest_auth() {
+    let authorized = Arc::new(Mutex::new(Vec::new()));
+    let server = digest_proxy(authorized.clone());
+
+    let proxy = format!("http://{}", server.addr());
+    let res = reqwest::Client::builder()
+        .proxy(
+            reqwest::Proxy::http(&proxy)
+                .unwrap()
+                .digest_auth("Aladdin", "open sesame"),
+        )
+        .build()
+        .unwrap()
+        .post("http://hyper.rs/prox")
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let authorized = authorized.lock().unwrap();
+    assert_eq!(authorized.len(), 1);
+    assert!(authorized[0].starts_with(
+        r#"Digest username="Aladdin", realm="proxy", nonce="abc", uri="http://hyper.rs/prox""#
+    ));
+}
+
+#[tokio::test]
+async fn http_proxy_auth_handler() {
+    let authorized = Arc::new(Mutex::new(Vec::new()));
+    let server = digest_proxy(authorized.clone());
+
+    let proxy = format!("http://{}", server.addr());
+    let res = reqwest::Client::builder()
+        .proxy(
+            reqwest::Proxy::http(&proxy)
+                .unwrap()
+                .auth_handler(|challenge| {
+                    assert_eq!(challenge.method(), "GET");
+                    assert_eq!(challenge.uri(), "http://hyper.rs/prox");
+                    assert_eq!(
+                        challenge.proxy_authenticate()[0],
+                        r#"Digest realm="proxy", nonce="abc", qop="auth""#
+                    );
+                    Some(reqwest::header::HeaderValue::from_static("Custom token"))
+                }),
+        )
+        .build()
+        .unwrap()
+        .get("http://hyper.rs/prox")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(*authorized.lock().unwrap(), vec!["Custom token"]);
+}
+
+#[tokio::test]
+async fn http_proxy_challenge_unanswered() {
+    let authorized = Arc::new(Mutex::new(Vec::new()));
+    let server = digest_proxy(authorized.clone());
+
+    let proxy = format!("http://{}", server.addr());
+    let res = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(&proxy).unwrap().auth_handler(|_| None))
+        .build()
+        .unwrap()
+        .get("http://hyper.rs/prox")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        res.status(),
+        reqwest::StatusCode::PROXY_AUTHENTICATION_REQUIRED
+    );
+    assert!(authorized.lock().unwrap().is_empty());
+}
+
+#[cfg(feature = "__tls")]
+#[tokio::test]
+async fn tunnel_digest_auth() {
+    let authorized = Arc::new(Mutex::new(Vec::new()));
+    let server = digest_proxy(authorized.clone());
+
+    let proxy = format!("http://{}", server.addr());
+    // The proxy stand-in doesn't tunnel, so the TLS handshake fails once
+    // the `CONNECT` is authorized.
+    let err = reqwest::Client::builder()
+        .proxy(
+            reqwest::Proxy::https(&proxy)
+                .unwrap()
+                .digest_auth("Aladdin", "open sesame"),
+        )
+        .build()
+        .unwrap()
+        .get("https://hyper.rs/prox")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.url().map(|u| u.as_str()), Some("https://hyper.rs/prox"));
+    let authorized = authorized.lock().unwrap();
+    assert_eq!(authorized.len(), 1);
+    assert!(authorized[0].starts_with(
+        r#"Digest username="Aladdin", realm="proxy", nonce="abc", uri="hyper.rs:443""#
+    ));
+}
@@ -0,0 +1,239 @@
+mod support;
+use support::*;
+
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{retry, StatusCode};
+
+fn policy(max_retries: usize) -> retry::Policy {
+    retry::Policy::limited(max_retries).backoff(Duration::from_millis(1), Duration::from_millis(10))
+}
+
+/// A server answering `503 Service Unavailable` to the first `failures`
+/// requests, and `200 OK` afterwards.
+fn flaky(failures: usize, hits: Arc<AtomicUsize>) -> server::Server {
+    server::http(move |req| {
+        let hit = hits.fetch_add(1, Ordering::SeqCst);
+        async move {
+            assert_eq!(req.uri(), "/flaky");
+            let status = if hit < failures {
+                StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                StatusCode::OK
+            };
+            http::Response::builder()
+                .status(status)
+                .body(Default::default())
+                .unwrap()
+        }
+    })
+}
+
+#[tokio::test]
+async fn retries_status_until_success() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server = flaky(2, hits.clone());
+
+    let url = format!("http://{}/flaky", server.addr());
+    let res = reqwest::Client::builder()
+        .retry(policy(3))
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn retries_exhausted_returns_last_response() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server = flaky(100, hits.clone());
+
+    let url = format!("http://{}/flaky", server.addr());
+    let res = reqwest::Client::builder()
+        .retry(policy(2))
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn request_policy_overrides_client() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server = flaky(1, hits.clone());
+
+    let url = format!("http://{}/flaky", server.addr());
+    let client = reqwest::Client::builder().retry(policy(3)).build().unwrap();
+
+    let res = client
+        .get(&url)
+        .retry(retry::Policy::none())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server = flaky(1, hits.clone());
+
+    let url = format!("http://{}/flaky", server.addr());
+    let res = reqwest::Client::new()
+        .get(&url)
+        .retry(policy(1))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn non_idempotent_method_is_not_retried() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server = flaky(1, hits.clone());
+
+    let url = format!("http://{}/flaky", server.addr());
+    let res = reqwest::Client::builder()
+        .retry(policy(3))
+        .build()
+        .unwrap()
+        .post(&url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn retry_resends_reusable_body() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server = server::http({
+        let hits = hits.clone();
+        move |req| {
+            let hit = hits.fetch_add(1, Ordering::SeqCst);
+            async move {
+                assert_eq!(req.method(), "PUT");
+                assert_eq!(req.headers()["content-length"], "5");
+                let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                assert_eq!(body, "hello");
+
+                let status = if hit == 0 {
+                    StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    StatusCode::OK
+                };
+                http::Response::builder()
+                    .status(status)
+                    .body(Default::default())
+                    .unwrap()
+            }
+        }
+    });
+
+    let url = format!("http://{}/put", server.addr());
+    let res = reqwest::Client::builder()
+        .retry(policy(1))
+        .build()
+        .unwrap()
+        .put(&url)
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn retry_after_header_is_honored() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server = server::http({
+        let hits = hits.clone();
+        move |_req| {
+            let hit = hits.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if hit == 0 {
+                    http::Response::builder()
+                        .status(StatusCode::TOO_MANY_REQUESTS)
+                        .header("retry-after", "1")
+                        .body(Default::default())
+                        .unwrap()
+                } else {
+                    http::Response::default()
+                }
+            }
+        }
+    });
+
+    let url = format!("http://{}/retry-after", server.addr());
+    let start = std::time::Instant::now();
+    let res = reqwest::Client::builder()
+        .retry(retry::Policy::limited(1))
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+    assert!(start.elapsed() >= Duration::from_secs(1));
+}
+
+/// Fails the first lookup, so the first connection attempt errors.
+struct FailOnce {
+    lookups: AtomicUsize,
+}
+
+impl Resolve for FailOnce {
+    fn resolve(&self, _name: Name) -> Resolving {
+        let first = self.lookups.fetch_add(1, Ordering::SeqCst) == 0;
+        Box::pin(async move {
+            if first {
+                return Err("temporary failure".into());
+            }
+            let addrs: Addrs = Box::new(vec![IpAddr::from([127, 0, 0, 1])].into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[tokio::test]
+async fn retries_connect_error() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let url = format!("http://retry.invalid:{}/connect", server.addr().port());
+    let res = reqwest::Client::builder()
+        .no_proxy()
+        .dns_resolver(Arc::new(FailOnce {
+            lookups: AtomicUsize::new(0),
+        }))
+        .retry(policy(1))
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+}
@@ -0,0 +1,221 @@
+mod support;
+use support::*;
+
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+
+/// The destination a SOCKS5 client asked the stand-in proxy to connect to.
+#[derive(Debug, PartialEq)]
+enum Target {
+    Ip(IpAddr, u16),
+    Domain(String, u16),
+}
+
+/// Starts a minimal SOCKS5 proxy that relays every CONNECT to `upstream`,
+/// reporting the requested destination on the returned channel.
+fn socks5(
+    upstream: SocketAddr,
+    creds: Option<(&'static str, &'static str)>,
+) -> (SocketAddr, mpsc::Receiver<Target>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        for sock in listener.incoming() {
+            let sock = sock.unwrap();
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let _ = handshake_and_relay(sock, upstream, creds, tx);
+            });
+        }
+    });
+
+    (addr, rx)
+}
+
+fn handshake_and_relay(
+    mut sock: TcpStream,
+    upstream: SocketAddr,
+    creds: Option<(&'static str, &'static str)>,
+    tx: mpsc::Sender<Target>,
+) -> io::Result<()> {
+    // greeting
+    let mut buf = [0u8; 2];
+    sock.read_exact(&mut buf)?;
+    assert_eq!(buf[0], 5, "socks version");
+    let mut methods = vec![0u8; buf[1] as usize];
+    sock.read_exact(&mut methods)?;
+
+    if let Some((user, pass)) = creds {
+        assert!(methods.contains(&2), "client should offer username/password");
+        sock.write_all(&[5, 2])?;
+
+        let mut ver_ulen = [0u8; 2];
+        sock.read_exact(&mut ver_ulen)?;
+        let mut username = vec![0u8; ver_ulen[1] as usize];
+        sock.read_exact(&mut username)?;
+        let mut plen = [0u8; 1];
+        sock.read_exact(&mut plen)?;
+        let mut password = vec![0u8; plen[0] as usize];
+        sock.read_exact(&mut password)?;
+
+        assert_eq!(username, user.as_bytes());
+        assert_eq!(password, pass.as_bytes());
+        sock.write_all(&[1, 0])?;
+    } else {
+        assert!(methods.contains(&0), "client should offer no authentication");
+        sock.write_all(&[5, 0])?;
+    }
+
+    // request
+    let mut req = [0u8; 4];
+    sock.read_exact(&mut req)?;
+    assert_eq!(&req[..3], &[5, 1, 0], "CONNECT request");
+    let target = match req[3] {
+        1 => {
+            let mut ip = [0u8; 4];
+            sock.read_exact(&mut ip)?;
+            Target::Ip(Ipv4Addr::from(ip).into(), read_port(&mut sock)?)
+        }
+        3 => {
+            let mut len = [0u8; 1];
+            sock.read_exact(&mut len)?;
+            let mut domain = vec![0u8; len[0] as usize];
+            sock.read_exact(&mut domain)?;
+            let domain = String::from_utf8(domain).unwrap();
+            Target::Domain(domain, read_port(&mut sock)?)
+        }
+        4 => {
+            let mut ip = [0u8; 16];
+            sock.read_exact(&mut ip)?;
+            Target::Ip(Ipv6Addr::from(ip).into(), read_port(&mut sock)?)
+        }
+        other => panic!("unknown address type: {}", other),
+    };
+    tx.send(target).unwrap();
+
+    let upstream = TcpStream::connect(upstream)?;
+    sock.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])?;
+
+    let mut client_read = sock.try_clone()?;
+    let mut upstream_write = upstream.try_clone()?;
+    thread::spawn(move || {
+        let _ = io::copy(&mut client_read, &mut upstream_write);
+    });
+    let mut upstream_read = upstream;
+    io::copy(&mut upstream_read, &mut sock)?;
+    Ok(())
+}
+
+fn read_port(sock: &mut TcpStream) -> io::Result<u16> {
+    let mut port = [0u8; 2];
+    sock.read_exact(&mut port)?;
+    Ok(u16::from_be_bytes(port))
+}
+
+#[tokio::test]
+async fn socks5_resolves_locally() {
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), "/socks5");
+        async { http::Response::default() }
+    });
+    let (proxy, targets) = socks5(server.addr(), None);
+
+    let url = format!("http://localhost:{}/socks5", server.addr().port());
+    let res = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::all(&format!("socks5://{}", proxy)).unwrap())
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.url().as_str(), &url);
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    match targets.recv().unwrap() {
+        Target::Ip(ip, port) => {
+            assert!(ip.is_loopback(), "{} should be loopback", ip);
+            assert_eq!(port, server.addr().port());
+        }
+        other => panic!("socks5 should resolve locally: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn socks5h_resolves_remotely() {
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), "/socks5h");
+        assert_eq!(req.headers()["host"], "not.a.real.host.invalid");
+        async { http::Response::default() }
+    });
+    let (proxy, targets) = socks5(server.addr(), None);
+
+    let url = "http://not.a.real.host.invalid/socks5h";
+    let res = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::all(&format!("socks5h://{}", proxy)).unwrap())
+        .build()
+        .unwrap()
+        .get(url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.url().as_str(), url);
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        targets.recv().unwrap(),
+        Target::Domain("not.a.real.host.invalid".into(), 80)
+    );
+}
+
+#[tokio::test]
+async fn socks5_auth_from_url() {
+    let server = server::http(move |_req| async { http::Response::default() });
+    let (proxy, targets) = socks5(server.addr(), Some(("Aladdin", "open sesame")));
+
+    let url = "http://not.a.real.host.invalid/auth";
+    let res = reqwest::Client::builder()
+        .proxy(
+            reqwest::Proxy::all(&format!("socks5h://Aladdin:open%20sesame@{}", proxy)).unwrap(),
+        )
+        .build()
+        .unwrap()
+        .get(url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        targets.recv().unwrap(),
+        Target::Domain("not.a.real.host.invalid".into(), 80)
+    );
+}
+
+#[cfg(feature = "__tls")]
+#[tokio::test]
+async fn socks5h_https_handshakes_through_tunnel() {
+    // The stand-in relays to a plain HTTP server, so the TLS handshake
+    // started inside the tunnel is expected to fail.
+    let server = server::http(move |_req| async { http::Response::default() });
+    let (proxy, targets) = socks5(server.addr(), None);
+
+    reqwest::Client::builder()
+        .proxy(reqwest::Proxy::all(&format!("socks5h://{}", proxy)).unwrap())
+        .build()
+        .unwrap()
+        .get("https://not.a.real.host.invalid/tls")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        targets.recv().unwrap(),
+        Target::Domain("not.a.real.host.invalid".into(), 443)
+    );
+}
@@ -0,0 +1,72 @@
+mod support;
+use support::*;
+
+use std::time::Duration;
+
+#[tokio::test]
+async fn stats_count_connections_and_bytes() {
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let client = reqwest::Client::new();
+    assert_eq!(client.stats(), reqwest::Stats::default());
+
+    let url = format!("http://{}/stats", server.addr());
+    for _ in 0..2 {
+        let res = client.get(&url).send().await.unwrap();
+        assert_eq!(res.text().await.unwrap(), "Hello");
+        // Give the connection time to go back to the pool.
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+    }
+
+    let stats = client.stats();
+    assert_eq!(stats.connections_created(), 1);
+    assert_eq!(stats.connections_reused(), 1);
+    assert_eq!(stats.requests_in_flight(), 0);
+    assert_eq!(stats.redirects_followed(), 0);
+    assert!(stats.bytes_sent() > 0);
+    assert!(stats.bytes_received() > 2 * "Hello".len() as u64);
+}
+
+#[tokio::test]
+async fn stats_count_redirects() {
+    let server = server::http(move |req| {
+        async move {
+            if req.uri() == "/redirect" {
+                http::Response::builder()
+                    .status(302)
+                    .header("location", "/dst")
+                    .body(Default::default())
+                    .unwrap()
+            } else {
+                http::Response::default()
+            }
+        }
+    });
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/redirect", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+
+    assert_eq!(res.url().path(), "/dst");
+    assert_eq!(client.stats().redirects_followed(), 1);
+}
+
+#[tokio::test]
+async fn stats_count_requests_in_flight() {
+    let server = server::http(move |_req| {
+        async {
+            tokio::time::delay_for(Duration::from_millis(200)).await;
+            http::Response::default()
+        }
+    });
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/slow", server.addr());
+    let pending = tokio::spawn(client.get(&url).send());
+
+    tokio::time::delay_for(Duration::from_millis(100)).await;
+    assert_eq!(client.stats().requests_in_flight(), 1);
+
+    pending.await.unwrap().unwrap();
+    assert_eq!(client.stats().requests_in_flight(), 0);
+}
@@ -36,10 +36,120 @@ impl Drop for Server {
     }
 }
 
+#[allow(unused)]
 pub fn http<F, Fut>(func: F) -> Server
 where
     F: Fn(http::Request<hyper::Body>) -> Fut + Clone + Send + 'static,
     Fut: Future<Output = http::Response<hyper::Body>> + Send + 'static,
+{
+    spawn(move |shutdown_rx| async move {
+        let srv = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(
+            hyper::service::make_service_fn(move |_| {
+                let func = func.clone();
+                async move {
+                    Ok::<_, Infallible>(hyper::service::service_fn(move |req| {
+                        let fut = func(req);
+                        async move { Ok::<_, Infallible>(fut.await) }
+                    }))
+                }
+            }),
+        );
+
+        let addr = srv.local_addr();
+        let srv = srv.with_graceful_shutdown(async move {
+            let _ = shutdown_rx.await;
+        });
+        (addr, Box::pin(srv) as Serving)
+    })
+}
+
+/// The PEM certificate of the CA that issued the certificate of `https`
+/// servers, for `localhost`.
+///
+/// The certificates in `support/certs` were made with `openssl req` and
+/// `openssl x509 -req`, valid for 100 years.
+#[cfg(feature = "__tls")]
+#[allow(unused)]
+pub static CA_CERT: &[u8] = include_bytes!("certs/ca.pem");
+
+/// The SHA-256 hash of the public key of the certificate of `https` servers.
+#[cfg(feature = "__tls")]
+#[allow(unused)]
+pub static SERVER_SPKI_SHA256: [u8; 32] = [
+    0x7a, 0xfc, 0xfb, 0x7b, 0xa7, 0x66, 0x91, 0x9e, 0xa4, 0xeb, 0xec, 0x20, 0x9d, 0x8a, 0x4b, 0x1b,
+    0x82, 0x53, 0xf4, 0x36, 0xaa, 0xbb, 0x11, 0x0d, 0x3f, 0x4b, 0x95, 0x8c, 0x10, 0x9f, 0x44, 0xc1,
+];
+
+/// Like `http`, but over TLS, with a certificate for `localhost` issued by
+/// `CA_CERT`.
+///
+/// Connections failing the TLS handshake are dropped.
+#[cfg(feature = "__tls")]
+#[allow(unused)]
+pub fn https<F, Fut>(func: F) -> Server
+where
+    F: Fn(http::Request<hyper::Body>) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = http::Response<hyper::Body>> + Send + 'static,
+{
+    use futures_util::{stream, StreamExt};
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use tokio_rustls::rustls::internal::pemfile;
+    use tokio_rustls::rustls::{NoClientAuth, ServerConfig};
+
+    let certs = pemfile::certs(&mut Cursor::new(&include_bytes!("certs/server.pem")[..]))
+        .expect("server.pem");
+    let key =
+        pemfile::pkcs8_private_keys(&mut Cursor::new(&include_bytes!("certs/server.key")[..]))
+            .expect("server.key")
+            .remove(0);
+    let mut tls = ServerConfig::new(NoClientAuth::new());
+    tls.set_single_cert(certs, key).expect("server certificate");
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(tls));
+
+    spawn(move |shutdown_rx| async move {
+        let listener = tokio::net::TcpListener::bind(net::SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .expect("bind");
+        let addr = listener.local_addr().expect("local_addr");
+        let incoming = stream::unfold(listener, |mut listener| async move {
+            let accepted = listener.accept().await;
+            Some((accepted, listener))
+        })
+        .filter_map(move |accepted| {
+            let acceptor = acceptor.clone();
+            async move {
+                let (tcp, _) = accepted.ok()?;
+                let tls = acceptor.accept(tcp).await.ok()?;
+                Some(Ok::<_, std::io::Error>(tls))
+            }
+        });
+
+        let srv = hyper::Server::builder(hyper::server::accept::from_stream(incoming))
+            .serve(hyper::service::make_service_fn(move |_| {
+                let func = func.clone();
+                async move {
+                    Ok::<_, Infallible>(hyper::service::service_fn(move |req| {
+                        let fut = func(req);
+                        async move { Ok::<_, Infallible>(fut.await) }
+                    }))
+                }
+            }))
+            .with_graceful_shutdown(async move {
+                let _ = shutdown_rx.await;
+            });
+        (addr, Box::pin(srv) as Serving)
+    })
+}
+
+type Serving = std::pin::Pin<Box<dyn Future<Output = hyper::Result<()>> + Send>>;
+
+/// Runs the server that `bind` makes on a thread of its own, until the
+/// returned `Server` is dropped.
+fn spawn<B, Fut>(bind: B) -> Server
+where
+    B: FnOnce(oneshot::Receiver<()>) -> Fut + Send + 'static,
+    Fut: Future<Output = (net::SocketAddr, Serving)>,
 {
     //Spawn new runtime in thread to prevent reactor execution context conflict
     thread::spawn(move || {
@@ -48,25 +158,8 @@ where
             .enable_all()
             .build()
             .expect("new rt");
-        let srv = rt.block_on(async move {
-            hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(hyper::service::make_service_fn(
-                move |_| {
-                    let func = func.clone();
-                    async move {
-                        Ok::<_, Infallible>(hyper::service::service_fn(move |req| {
-                            let fut = func(req);
-                            async move { Ok::<_, Infallible>(fut.await) }
-                        }))
-                    }
-                },
-            ))
-        });
-
-        let addr = srv.local_addr();
         let (shutdown_tx, shutdown_rx) = oneshot::channel();
-        let srv = srv.with_graceful_shutdown(async move {
-            let _ = shutdown_rx.await;
-        });
+        let (addr, srv) = rt.block_on(bind(shutdown_rx));
 
         let (panic_tx, panic_rx) = std_mpsc::channel();
         let tname = format!(
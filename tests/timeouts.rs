@@ -150,3 +150,101 @@ fn write_timeout_large_body() {
     assert!(err.is_timeout());
     assert_eq!(err.url().map(|u| u.as_str()), Some(url.as_str()));
 }
+
+/// Sends a body in chunks of "x", one every `interval`, then stalls for
+/// `stall` before the last one.
+fn chunked_body(chunks: usize, interval: Duration, stall: Duration) -> hyper::Body {
+    let (mut tx, body) = hyper::Body::channel();
+    tokio::spawn(async move {
+        for _ in 0..chunks {
+            tokio::time::delay_for(interval).await;
+            if tx.send_data("x".into()).await.is_err() {
+                return;
+            }
+        }
+        tokio::time::delay_for(stall).await;
+        let _ = tx.send_data("x".into()).await;
+    });
+    body
+}
+
+#[tokio::test]
+async fn read_timeout_allows_slow_body() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| {
+        async {
+            let body = chunked_body(5, Duration::from_millis(100), Duration::from_millis(0));
+            http::Response::new(body)
+        }
+    });
+
+    let client = reqwest::Client::builder()
+        .read_timeout(Duration::from_millis(300))
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/slow", server.addr());
+    let res = client.get(&url).send().await.expect("Failed to get");
+    let body = res.text().await.unwrap();
+
+    assert_eq!(body, "xxxxxx");
+}
+
+#[tokio::test]
+async fn read_timeout_stalled_body() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| {
+        async {
+            let body = chunked_body(1, Duration::from_millis(0), Duration::from_secs(2));
+            http::Response::new(body)
+        }
+    });
+
+    let client = reqwest::Client::builder()
+        .read_timeout(Duration::from_millis(300))
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/stalled", server.addr());
+    let mut res = client.get(&url).send().await.expect("Failed to get");
+    assert_eq!(res.chunk().await.unwrap().unwrap(), "x");
+
+    let err = res.chunk().await.unwrap_err();
+
+    assert!(err.is_timeout());
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn read_timeout_stalled_body_blocking() {
+    use std::io::Read;
+
+    let _ = env_logger::try_init();
+
+    let client = reqwest::blocking::Client::builder()
+        .read_timeout(Duration::from_millis(300))
+        .build()
+        .unwrap();
+
+    let server = server::http(move |_req| {
+        async {
+            let body = chunked_body(1, Duration::from_millis(0), Duration::from_secs(2));
+            http::Response::new(body)
+        }
+    });
+
+    let url = format!("http://{}/stalled", server.addr());
+    let mut res = client.get(&url).send().unwrap();
+
+    let mut body = String::new();
+    let err = res.read_to_string(&mut body).unwrap_err();
+    let err = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<reqwest::Error>())
+        .expect("reqwest::Error");
+
+    assert!(err.is_timeout());
+    assert_eq!(body, "x");
+}
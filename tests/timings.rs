@@ -0,0 +1,93 @@
+mod support;
+use support::*;
+
+use std::time::Duration;
+
+#[tokio::test]
+async fn timings_of_new_connection() {
+    let server = server::http(move |_req| {
+        async {
+            tokio::time::delay_for(Duration::from_millis(100)).await;
+            http::Response::default()
+        }
+    });
+
+    let url = format!("http://localhost:{}/timings", server.addr().port());
+    let res = reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+
+    let timings = res.timings();
+    assert!(timings.dns().is_some());
+    assert!(timings.connect().is_some());
+    assert_eq!(timings.tls(), None);
+    assert!(timings.ttfb() >= Duration::from_millis(100));
+    assert!(timings.total() >= timings.ttfb());
+    assert!(timings.redirects().is_empty());
+}
+
+#[tokio::test]
+async fn timings_without_dns() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let url = format!("http://{}/timings", server.addr());
+    let res = reqwest::get(&url).await.unwrap();
+
+    let timings = res.timings();
+    assert_eq!(timings.dns(), None);
+    assert!(timings.connect().is_some());
+}
+
+#[tokio::test]
+async fn timings_of_pooled_connection() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/timings", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert!(res.timings().connect().is_some());
+    res.text().await.unwrap();
+    // Give the connection time to go back to the pool.
+    tokio::time::delay_for(Duration::from_millis(50)).await;
+
+    let res = client.get(&url).send().await.unwrap();
+    let timings = res.timings();
+    assert_eq!(timings.dns(), None);
+    assert_eq!(timings.connect(), None);
+    assert_eq!(timings.tls(), None);
+    assert_eq!(timings.ttfb(), timings.total());
+}
+
+#[tokio::test]
+async fn timings_of_redirects() {
+    let server = server::http(move |req| {
+        async move {
+            if req.uri() == "/redirect" {
+                http::Response::builder()
+                    .status(302)
+                    .header("location", "/dst")
+                    .body(Default::default())
+                    .unwrap()
+            } else {
+                tokio::time::delay_for(Duration::from_millis(100)).await;
+                http::Response::default()
+            }
+        }
+    });
+
+    let url = format!("http://{}/redirect", server.addr());
+    let res = reqwest::get(&url).await.unwrap();
+
+    let timings = res.timings();
+    assert_eq!(res.url().path(), "/dst");
+    assert!(timings.ttfb() >= Duration::from_millis(100));
+    assert_eq!(timings.redirects().len(), 1);
+    assert!(timings.redirects()[0].connect().is_some());
+    assert!(timings.redirects()[0].ttfb() < Duration::from_millis(100));
+}
@@ -0,0 +1,414 @@
+#![cfg(feature = "__tls")]
+mod support;
+use support::*;
+
+fn https_client() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .no_proxy()
+        .add_root_certificate(reqwest::Certificate::from_pem(server::CA_CERT).unwrap())
+}
+
+fn localhost(server: &server::Server) -> String {
+    format!("https://localhost:{}/", server.addr().port())
+}
+
+#[tokio::test]
+async fn pin_certificate_match() {
+    let server = server::https(move |_req| async { http::Response::default() });
+
+    let res = https_client()
+        .resolve("localhost", server.addr())
+        .pin_certificate("example.com", [0; 32])
+        .pin_certificate("localhost", [0; 32])
+        .pin_certificate("LOCALHOST", server::SERVER_SPKI_SHA256)
+        .build()
+        .unwrap()
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn pin_certificate_mismatch() {
+    let server = server::https(move |_req| async { http::Response::default() });
+
+    let err = https_client()
+        .resolve("localhost", server.addr())
+        .pin_certificate("localhost", [0; 32])
+        .build()
+        .unwrap()
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_certificate_pin(), "{:?}", err);
+}
+
+#[tokio::test]
+async fn pin_certificate_other_host() {
+    let server = server::https(move |_req| async { http::Response::default() });
+
+    let res = https_client()
+        .resolve("localhost", server.addr())
+        .pin_certificate("example.com", [0; 32])
+        .build()
+        .unwrap()
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[cfg(feature = "__rustls")]
+#[tokio::test]
+async fn rustls_pin_certificate() {
+    let server = server::https(move |_req| async { http::Response::default() });
+
+    let client = |pin| {
+        https_client()
+            .use_rustls_tls()
+            .resolve("localhost", server.addr())
+            .pin_certificate("localhost", pin)
+            .build()
+            .unwrap()
+    };
+
+    let res = client(server::SERVER_SPKI_SHA256)
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let err = client([0; 32])
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_certificate_pin(), "{:?}", err);
+}
+
+#[tokio::test]
+async fn pin_certificate_through_tunnel() {
+    let server = server::https(move |_req| async { http::Response::default() });
+    let target = server.addr();
+    let proxy = server::http(move |req| {
+        assert_eq!(req.method(), "CONNECT");
+        tokio::spawn(async move {
+            let upgraded = req.into_body().on_upgrade().await.unwrap();
+            let tcp = tokio::net::TcpStream::connect(target).await.unwrap();
+            let (mut client_rd, mut client_wr) = tokio::io::split(upgraded);
+            let (mut server_rd, mut server_wr) = tokio::io::split(tcp);
+            let _ = futures_util::future::join(
+                tokio::io::copy(&mut client_rd, &mut server_wr),
+                tokio::io::copy(&mut server_rd, &mut client_wr),
+            )
+            .await;
+        });
+        async { http::Response::default() }
+    });
+
+    let client = |pin| {
+        https_client()
+            .proxy(reqwest::Proxy::https(&format!("http://{}", proxy.addr())).unwrap())
+            .pin_certificate("localhost", pin)
+            .build()
+            .unwrap()
+    };
+
+    let url = format!("https://localhost:{}/", target.port());
+    let res = client(server::SERVER_SPKI_SHA256)
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let err = client([0; 32]).get(&url).send().await.unwrap_err();
+    assert!(err.is_certificate_pin(), "{:?}", err);
+}
+
+#[tokio::test]
+async fn built_in_root_certs_disabled() {
+    let server = server::https(move |_req| async { http::Response::default() });
+
+    let res = https_client()
+        .tls_built_in_root_certs(false)
+        .resolve("localhost", server.addr())
+        .build()
+        .unwrap()
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[cfg(feature = "__rustls")]
+#[tokio::test]
+async fn rustls_built_in_root_certs_disabled() {
+    let server = server::https(move |_req| async { http::Response::default() });
+
+    let res = https_client()
+        .use_rustls_tls()
+        .tls_built_in_root_certs(false)
+        .resolve("localhost", server.addr())
+        .build()
+        .unwrap()
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+/// Makes `CA_CERT` part of the system's trust store, which is looked up
+/// in `SSL_CERT_FILE` first on Linux.
+#[cfg(target_os = "linux")]
+#[allow(unused)]
+fn trust_ca_from_system() {
+    std::env::set_var(
+        "SSL_CERT_FILE",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support/certs/ca.pem"),
+    );
+}
+
+#[cfg(all(feature = "default-tls", target_os = "linux"))]
+#[tokio::test]
+async fn built_in_root_certs_from_system() {
+    let server = server::https(move |_req| async { http::Response::default() });
+    trust_ca_from_system();
+
+    let client = |built_in| {
+        reqwest::Client::builder()
+            .no_proxy()
+            .tls_built_in_root_certs(built_in)
+            .resolve("localhost", server.addr())
+            .build()
+            .unwrap()
+    };
+
+    let res = client(true).get(&localhost(&server)).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    client(false)
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap_err();
+}
+
+#[cfg(all(feature = "rustls-tls-native-roots", target_os = "linux"))]
+#[tokio::test]
+async fn rustls_native_root_certs() {
+    let server = server::https(move |_req| async { http::Response::default() });
+    trust_ca_from_system();
+
+    let client = |built_in| {
+        reqwest::Client::builder()
+            .use_rustls_tls()
+            .no_proxy()
+            .tls_built_in_root_certs(built_in)
+            .resolve("localhost", server.addr())
+            .build()
+            .unwrap()
+    };
+
+    let res = client(true).get(&localhost(&server)).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    client(false)
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+async fn tls_version_min_greater_than_max() {
+    let err = reqwest::Client::builder()
+        .min_tls_version(reqwest::TlsVersion::TLS_1_2)
+        .max_tls_version(reqwest::TlsVersion::TLS_1_1)
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder(), "{:?}", err);
+}
+
+#[cfg(feature = "default-tls")]
+#[tokio::test]
+async fn native_tls_versions() {
+    let server = server::https(move |_req| async { http::Response::default() });
+
+    let res = https_client()
+        .resolve("localhost", server.addr())
+        .min_tls_version(reqwest::TlsVersion::TLS_1_2)
+        .build()
+        .unwrap()
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // The server only speaks TLS 1.2 and 1.3.
+    https_client()
+        .resolve("localhost", server.addr())
+        .max_tls_version(reqwest::TlsVersion::TLS_1_1)
+        .build()
+        .unwrap()
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap_err();
+}
+
+#[cfg(feature = "default-tls")]
+#[test]
+fn native_tls_unsupported_options() {
+    let err = reqwest::Client::builder()
+        .min_tls_version(reqwest::TlsVersion::TLS_1_3)
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder(), "{:?}", err);
+
+    let err = reqwest::Client::builder()
+        .tls_cipher_suites(&["TLS_AES_128_GCM_SHA256"])
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder(), "{:?}", err);
+}
+
+#[cfg(feature = "__rustls")]
+#[tokio::test]
+async fn rustls_tls_versions_and_cipher_suites() {
+    let server = server::https(move |_req| async { http::Response::default() });
+
+    let client = |builder: reqwest::ClientBuilder| {
+        builder
+            .use_rustls_tls()
+            .resolve("localhost", server.addr())
+            .build()
+            .unwrap()
+    };
+
+    let res = client(https_client().min_tls_version(reqwest::TlsVersion::TLS_1_3))
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client(
+        https_client()
+            .max_tls_version(reqwest::TlsVersion::TLS_1_2)
+            .tls_cipher_suites(&["TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"]),
+    )
+    .get(&localhost(&server))
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // No TLS 1.2 suite is left to offer.
+    client(
+        https_client()
+            .max_tls_version(reqwest::TlsVersion::TLS_1_2)
+            .tls_cipher_suites(&["TLS_AES_128_GCM_SHA256"]),
+    )
+    .get(&localhost(&server))
+    .send()
+    .await
+    .unwrap_err();
+}
+
+#[cfg(feature = "__rustls")]
+#[test]
+fn rustls_unsupported_options() {
+    let err = reqwest::Client::builder()
+        .use_rustls_tls()
+        .max_tls_version(reqwest::TlsVersion::TLS_1_1)
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder(), "{:?}", err);
+
+    let err = reqwest::Client::builder()
+        .use_rustls_tls()
+        .tls_cipher_suites(&["TLS_RSA_WITH_RC4_128_SHA"])
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder(), "{:?}", err);
+}
+
+#[cfg(feature = "default-tls")]
+#[tokio::test]
+async fn preconfigured_native_tls() {
+    let server = server::https(move |_req| async { http::Response::default() });
+
+    let tls = native_tls_crate::TlsConnector::builder()
+        .add_root_certificate(native_tls_crate::Certificate::from_pem(server::CA_CERT).unwrap())
+        .build()
+        .unwrap();
+
+    let res = reqwest::Client::builder()
+        .no_proxy()
+        .use_preconfigured_tls(tls)
+        .resolve("localhost", server.addr())
+        .build()
+        .unwrap()
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[cfg(feature = "__rustls")]
+#[tokio::test]
+async fn preconfigured_rustls() {
+    let server = server::https(move |_req| async { http::Response::default() });
+
+    let mut tls = tokio_rustls::rustls::ClientConfig::new();
+    tls.root_store
+        .add_pem_file(&mut std::io::Cursor::new(server::CA_CERT))
+        .unwrap();
+
+    let client = |tls, pin| {
+        reqwest::Client::builder()
+            .no_proxy()
+            .use_preconfigured_tls(tls)
+            .resolve("localhost", server.addr())
+            .pin_certificate("localhost", pin)
+            .build()
+            .unwrap()
+    };
+
+    let res = client(tls.clone(), server::SERVER_SPKI_SHA256)
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // Pins are still checked with a preconfigured backend.
+    let err = client(tls, [0; 32])
+        .get(&localhost(&server))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_certificate_pin(), "{:?}", err);
+}
+
+#[test]
+fn preconfigured_unknown_backend() {
+    let err = reqwest::Client::builder()
+        .use_preconfigured_tls(0u8)
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder(), "{:?}", err);
+}
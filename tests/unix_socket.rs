@@ -0,0 +1,130 @@
+#![cfg(unix)]
+
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::thread;
+
+use tokio::net::UnixListener;
+use tokio::runtime;
+
+/// Serves HTTP on a new Unix socket, answering each request with its `Host`
+/// header and URI, and redirecting `/redirect` to `/dst`.
+fn server(name: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("reqwest-test-{}-{}.sock", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    let socket = path.clone();
+    thread::spawn(move || {
+        let mut rt = runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()
+            .expect("new rt");
+        rt.block_on(async move {
+            let mut listener = UnixListener::bind(&socket).expect("bind");
+            ready_tx.send(()).unwrap();
+            loop {
+                let (stream, _) = listener.accept().await.expect("accept");
+                let service =
+                    hyper::service::service_fn(|req: http::Request<hyper::Body>| async move {
+                        let res = if req.uri() == "/redirect" {
+                            http::Response::builder()
+                                .status(302)
+                                .header("location", "/dst")
+                                .body(hyper::Body::empty())
+                                .unwrap()
+                        } else {
+                            let host = req.headers()["host"].to_str().unwrap();
+                            http::Response::new(format!("{} {}", host, req.uri()).into())
+                        };
+                        Ok::<_, Infallible>(res)
+                    });
+                tokio::spawn(hyper::server::conn::Http::new().serve_connection(stream, service));
+            }
+        });
+    });
+    ready_rx.recv().unwrap();
+    path
+}
+
+#[tokio::test]
+async fn unix_socket() {
+    let path = server("get");
+
+    let client = reqwest::Client::builder()
+        .unix_socket(&path)
+        .build()
+        .unwrap();
+
+    let res = client
+        .get("http://localhost/containers/json")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.url().as_str(), "http://localhost/containers/json");
+    assert_eq!(res.text().await.unwrap(), "localhost /containers/json");
+}
+
+#[tokio::test]
+async fn unix_socket_redirect() {
+    let path = server("redirect");
+
+    let client = reqwest::Client::builder()
+        .unix_socket(&path)
+        .build()
+        .unwrap();
+
+    let res = client
+        .get("http://localhost/redirect")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.url().as_str(), "http://localhost/dst");
+    assert_eq!(res.text().await.unwrap(), "localhost /dst");
+}
+
+#[tokio::test]
+async fn unix_socket_missing() {
+    let path =
+        std::env::temp_dir().join(format!("reqwest-test-{}-missing.sock", std::process::id()));
+
+    let client = reqwest::Client::builder()
+        .unix_socket(&path)
+        .build()
+        .unwrap();
+
+    let err = client.get("http://localhost/").send().await.unwrap_err();
+
+    assert_eq!(err.url().map(|u| u.as_str()), Some("http://localhost/"));
+    let mut source = std::error::Error::source(&err);
+    let mut found = false;
+    while let Some(e) = source {
+        found |= e
+            .downcast_ref::<std::io::Error>()
+            .map_or(false, |e| e.kind() == std::io::ErrorKind::NotFound);
+        source = e.source();
+    }
+    assert!(found, "{:?}", err);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn unix_socket_blocking() {
+    let path = server("blocking");
+
+    let client = reqwest::blocking::Client::builder()
+        .unix_socket(&path)
+        .build()
+        .unwrap();
+
+    let res = client.get("http://localhost/blocking").send().unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.text().unwrap(), "localhost /blocking");
+}